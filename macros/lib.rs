@@ -1,11 +1,14 @@
-use proc_macro2::{Ident, Span, TokenStream};
+use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
 use proc_macro_error::*;
-use quote::quote;
+use quote::{format_ident, quote};
+use syn::parse::{ParseStream, Parser};
 use syn::punctuated::Punctuated;
 use syn::{
-    parse_quote, Attribute, Expr, ExprField, ExprPath, ExprTuple, FnArg, ForeignItemFn,
-    GenericArgument, GenericParam, Index, ItemFn, Member, Pat, PatIdent, PatType, Path,
-    PathArguments, PathSegment, ReturnType, Signature, Token, Type, TypePath, TypeReference,
+    parse_quote, Attribute, Expr, ExprField, ExprPath, ExprTuple, Field, Fields, FnArg,
+    ForeignItemFn, GenericArgument, GenericParam, ImplItem, ImplItemMethod, Index, Item, ItemFn,
+    ItemImpl, ItemMod, ItemStruct, ItemTrait, Lifetime, Lit, LitBool, LitStr, Member, Meta,
+    MetaNameValue, NestedMeta, Pat, PatIdent, PatType, Path, PathArguments, PathSegment,
+    ReturnType, Signature, Token, TraitItem, TraitItemMethod, Type, TypePath, TypeReference,
     TypeTuple, Visibility, WhereClause,
 };
 
@@ -137,19 +140,822 @@ fn generic_args_phantom(generics: &Punctuated<GenericArgument, Token![,]>) -> Ty
     }))
 }
 
+/// Parses a duration such as `30s` or `1h30m`, and returns it in milliseconds
+fn parse_duration(lit: &LitStr) -> Option<u64> {
+    let text = lit.value();
+    let mut rest = text.trim();
+    let mut total = 0u64;
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount = rest[..digits].parse::<u64>().ok()?;
+        rest = &rest[digits..];
+
+        let unit = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit] {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            _ => return None,
+        };
+        rest = &rest[unit..];
+
+        total = total.checked_add(amount.checked_mul(factor)?)?;
+    }
+
+    Some(total)
+}
+
+/// Options given as attribute parameters, as in `#[yeter::query(volatile)]`
+#[derive(Default)]
+struct QueryOptions {
+    /// The query reads state that is not tracked by the database
+    volatile: Option<Path>,
+    /// The query receives its previous output, as its second argument
+    incremental: Option<Path>,
+    /// The query is not memoized, its dependencies are attributed to the calling query
+    transparent: Option<Path>,
+    /// The query function returns a clone of the output, instead of an `Rc`
+    clone: Option<Path>,
+    /// The query function returns a reference to the output, that lives as long as the database
+    borrow: Option<Path>,
+    /// The query has no body, and calls the method of the installed implementation of a trait
+    interface: Option<Path>,
+    /// Whether `Err` outputs are cached like `Ok` ones, or only for the current revision
+    cache_err: Option<LitBool>,
+    /// How long results stay valid, in milliseconds
+    ttl: Option<(LitStr, u64)>,
+    /// What the query returns for inputs that were never set, if it is declared with
+    /// `#[yeter::input]`
+    unset_input: Option<UnsetInput>,
+}
+
+/// What an input query returns for inputs that were never set
+enum UnsetInput {
+    /// `None`, for `#[yeter::query]` functions without a body
+    None,
+    /// A default value, for `#[yeter::input(default = ...)]`
+    Default(Box<Expr>),
+    /// Nothing, it panics, for `#[yeter::input]`
+    Panic,
+}
+
+impl QueryOptions {
+    fn parse(attr: proc_macro::TokenStream) -> Self {
+        let mut options = QueryOptions::default();
+        let args = match Punctuated::<NestedMeta, Token![,]>::parse_terminated.parse(attr) {
+            Ok(args) => args,
+            Err(err) => {
+                emit_error!(err.span(), "{}", err);
+                return options;
+            }
+        };
+
+        for arg in args {
+            match arg {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("volatile") => {
+                    options.volatile = Some(path);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("incremental") => {
+                    options.incremental = Some(path);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("transparent") => {
+                    options.transparent = Some(path);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("clone") => {
+                    options.clone = Some(path);
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("borrow") => {
+                    options.borrow = Some(path);
+                }
+                NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("interface") => {
+                    match list.nested.iter().collect::<Vec<_>>().as_slice() {
+                        [NestedMeta::Meta(Meta::Path(path))] => {
+                            options.interface = Some(path.clone());
+                        }
+                        _ => emit_error!(
+                            list, "expected a trait";
+                            help = "use `interface(Trait)`";
+                        ),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Bool(lit),
+                    ..
+                })) if path.is_ident("cache_err") => {
+                    options.cache_err = Some(lit);
+                }
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(lit),
+                    ..
+                })) if path.is_ident("ttl") => match parse_duration(&lit) {
+                    Some(millis) => options.ttl = Some((lit, millis)),
+                    None => emit_error!(
+                        lit, "invalid duration";
+                        help = "use a duration such as \"500ms\", \"30s\" or \"1h30m\"";
+                    ),
+                },
+                arg => {
+                    emit_error!(
+                        arg, "unknown #[yeter::query] option";
+                        help = "available options are: `volatile`, `incremental`, `transparent`, \
+                            `clone`, `borrow`, `ttl = \"...\"`, `cache_err = false`, \
+                            `interface(Trait)`";
+                    );
+                }
+            }
+        }
+
+        options
+    }
+
+    /// Checks that the options can be used together, for a query that takes `args_after_db`
+    /// arguments after its database
+    fn check(&self, args_after_db: usize) {
+        if let Some(incremental) = &self.incremental {
+            if args_after_db == 0 {
+                emit_error!(
+                    incremental, "incremental queries must take their previous output as argument";
+                    help = "add a `previous: Option<yeter::Previous<T>>` argument after the database";
+                );
+            }
+            if let Some((ttl, _)) = &self.ttl {
+                emit_error!(ttl, "incremental queries can't have a time-to-live");
+            }
+        }
+
+        if let (Some(_), Some(borrow)) = (&self.clone, &self.borrow) {
+            emit_error!(borrow, "`clone` and `borrow` can't be used together");
+        }
+
+        if let Some(transparent) = &self.transparent {
+            let cached_options = [
+                self.incremental.as_ref().map(|_| "incremental"),
+                self.ttl.as_ref().map(|_| "ttl"),
+                self.cache_err.as_ref().map(|_| "cache_err"),
+            ];
+            for option in cached_options.into_iter().flatten() {
+                emit_error!(
+                    transparent, "transparent queries can't use the `{}` option", option;
+                    note = "their output is not cached";
+                );
+            }
+        }
+    }
+
+    /// The method of the database that runs the query
+    ///
+    /// `from_key` tells if the query can be re-executed from its key through [`Query`], in case
+    /// it creates tracked entities.
+    fn run_method(&self, from_key: bool) -> Ident {
+        // Queries with a time-to-live need to be refreshable to benefit from early cutoff
+        let name = match (&self.ttl, &self.incremental) {
+            _ if self.transparent.is_some() => "run_transparent",
+            (_, Some(_)) => "run_incremental",
+            (Some(_), None) => "run_refreshable",
+            (None, None) if from_key => "run_query",
+            (None, None) => "run",
+        };
+        Ident::new(name, Span::call_site())
+    }
+
+    /// The call to the method of the database that tries to run the query, which returns a
+    /// `Result` with a `CycleError`
+    fn try_run_call(
+        &self,
+        from_key: bool,
+        db: &TokenStream,
+        query_type: &TokenStream,
+        f: &TokenStream,
+        input: &TokenStream,
+    ) -> TokenStream {
+        let name = match (&self.ttl, &self.incremental) {
+            // Transparent queries are not on the query stack, so cycles are not detected
+            _ if self.transparent.is_some() => {
+                return quote! {
+                    ::std::result::Result::<_, ::yeter::CycleError>::Ok(
+                        #db.run_transparent::<_, #query_type>(#f, #input)
+                    )
+                };
+            }
+            (_, Some(_)) => "try_run_incremental",
+            (Some(_), None) => "try_run_refreshable",
+            (None, None) if from_key => "try_run_query",
+            (None, None) => "try_run",
+        };
+        let method = Ident::new(name, Span::call_site());
+        quote! { #db.#method::<_, #query_type>(#f, #input) }
+    }
+
+    /// The definition of [`QueryDef::TTL`], if the query has a time-to-live
+    fn ttl_const(&self) -> Option<TokenStream> {
+        self.ttl.as_ref().map(|(_, millis)| {
+            quote! {
+                const TTL: ::std::option::Option<::std::time::Duration> =
+                    ::std::option::Option::Some(::std::time::Duration::from_millis(#millis));
+            }
+        })
+    }
+
+    /// The return type of the query function, and how it is built from the `Rc` of the output
+    ///
+    /// The query function returns the output kept in the cache, a clone of it, or a reference to
+    /// it that lives as long as the database borrow, in which case it has a lifetime parameter.
+    fn return_output(
+        &self,
+        output_type: &Type,
+        output_ident: &Ident,
+        as_database: &TokenStream,
+    ) -> (Option<Lifetime>, TokenStream, TokenStream) {
+        let db_lifetime = Lifetime::new("'__yeter_db", Span::call_site());
+        match (&self.clone, &self.borrow) {
+            (Some(_), _) => (
+                None,
+                quote! { #output_type },
+                quote! { ::std::clone::Clone::clone(&*#output_ident) },
+            ),
+            (None, Some(_)) => (
+                Some(db_lifetime.clone()),
+                quote! { &#db_lifetime #output_type },
+                quote! { ::yeter::Database::borrow_output(#as_database, #output_ident) },
+            ),
+            (None, None) => (
+                None,
+                quote! { ::std::rc::Rc<#output_type> },
+                quote! { #output_ident },
+            ),
+        }
+    }
+
+    fn parse_input(attr: proc_macro::TokenStream) -> Self {
+        let parser = |input: ParseStream| {
+            if input.is_empty() {
+                return Ok(UnsetInput::Panic);
+            }
+
+            let name = input.parse::<Ident>()?;
+            if name != "default" {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "unknown #[yeter::input] option, expected `default = ...`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            let default = input.parse::<Expr>()?;
+            input.parse::<Option<Token![,]>>()?;
+
+            Ok(UnsetInput::Default(Box::new(default)))
+        };
+
+        let unset_input = parser.parse(attr).unwrap_or_else(|err| {
+            emit_error!(err.span(), "{}", err);
+            UnsetInput::Panic
+        });
+
+        QueryOptions {
+            unset_input: Some(unset_input),
+            ..Default::default()
+        }
+    }
+}
+
 #[proc_macro_error]
 #[proc_macro_attribute]
 pub fn query(
     attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    expand_query(QueryOptions::parse(attr), item)
+}
+
+#[proc_macro_error]
+#[proc_macro_attribute]
+pub fn input(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    match syn::parse::<ItemStruct>(item.clone()) {
+        Ok(item) => expand_input_struct(attr, item),
+        Err(_) => expand_query(QueryOptions::parse_input(attr), item),
+    }
+}
+
+/// Turns a struct into an id type, whose fields are stored in the database as input queries
+fn expand_input_struct(attr: proc_macro::TokenStream, item: ItemStruct) -> proc_macro::TokenStream {
+    if !attr.is_empty() {
+        emit_error!(
+            TokenStream::from(attr), "#[yeter::input] structs take no options";
+            help = "`default = ...` is only available on input queries";
+        );
+    }
+    if !item.generics.params.is_empty() {
+        abort!(item.generics, "#[yeter::input] structs can't be generic");
+    }
+    let fields = match &item.fields {
+        Fields::Named(fields) => &fields.named,
+        _ => abort!(item, "#[yeter::input] structs must have named fields"),
+    };
+
+    let ItemStruct {
+        attrs, vis, ident, ..
+    } = &item;
+    let attrs = attrs.iter().filter_map(without_id_derives);
+
+    let field_queries = fields.iter().enumerate().map(|(index, field)| {
+        let ty = &field.ty;
+        quote! { ::yeter::InputField<Self, #ty, #index> }
+    });
+    let field_names = fields
+        .iter()
+        .map(|field| field.ident.clone().unwrap())
+        .collect::<Vec<_>>();
+    let field_types = fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+
+    let new_doc = format!("Creates a new `{}` input with the given fields", ident);
+    let sets = field_queries
+        .clone()
+        .zip(&field_names)
+        .map(|(query, name)| quote! { db.set::<#query>((id,), #name); });
+
+    let accessors = fields.iter().zip(field_queries).map(|(field, query)| {
+        let Field {
+            attrs,
+            vis,
+            ident,
+            ty,
+            ..
+        } = field;
+        let setter = format_ident!("set_{}", ident.as_ref().unwrap());
+        let setter_doc = format!("Sets the `{}` field of this input", ident.as_ref().unwrap());
+
+        quote! {
+            #(#attrs)*
+            #vis fn #ident(self, db: &::yeter::Database) -> ::std::rc::Rc<#ty> {
+                db.run::<_, #query>(::yeter::Database::load_input::<#query>, (self,))
+            }
+
+            #[doc = #setter_doc]
+            #vis fn #setter(self, db: &::yeter::Database, value: #ty) {
+                db.set::<#query>((self,), value);
+            }
+        }
+    });
+
+    (quote! {
+        #(#attrs)*
+        #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        #vis struct #ident(::yeter::Id<#ident>);
+
+        impl #ident {
+            #[doc = #new_doc]
+            #vis fn new(db: &::yeter::Database, #(#field_names: #field_types),*) -> Self {
+                let id = #ident(db.new_input_id());
+                #(#sets)*
+                id
+            }
+
+            #(#accessors)*
+        }
+    })
+    .into()
+}
+
+/// Removes the traits that are derived for every input struct from a `#[derive(...)]` attribute
+///
+/// Returns `None` if no other traits are derived, and other attributes unchanged.
+fn without_id_derives(attr: &Attribute) -> Option<Attribute> {
+    const ID_DERIVES: [&str; 8] = [
+        "Clone",
+        "Copy",
+        "Debug",
+        "Eq",
+        "Hash",
+        "Ord",
+        "PartialEq",
+        "PartialOrd",
+    ];
+
+    if !attr.path.is_ident("derive") {
+        return Some(attr.clone());
+    }
+    let paths = match attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated) {
+        Ok(paths) => paths,
+        Err(_) => return Some(attr.clone()),
+    };
+    let is_id_derive = |path: &Path| {
+        let name = path.segments.last().map(|seg| seg.ident.to_string());
+        name.is_some_and(|name| ID_DERIVES.contains(&name.as_str()))
+    };
+
+    let paths = paths
+        .into_iter()
+        .filter(|path| !is_id_derive(path))
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        return None;
+    }
+    let mut attr = attr.clone();
+    attr.tokens = quote! { (#(#paths),*) };
+    Some(attr)
+}
+
+/// Checks if an attribute is `#[yeter::<name>]`, with or without arguments
+fn is_yeter_attr(attr: &Attribute, name: &str) -> bool {
+    let segments = &attr.path.segments;
+    segments.len() == 2 && segments[0].ident == "yeter" && segments[1].ident == name
+}
+
+/// Checks if a `#[yeter::query(...)]` attribute has an option such as `clone`
+fn has_query_option(attr: &Attribute, option: &str) -> bool {
+    let options = attr.parse_args_with(Punctuated::<NestedMeta, Token![,]>::parse_terminated);
+    options.is_ok_and(|options| {
+        options.iter().any(|arg| match arg {
+            NestedMeta::Meta(Meta::Path(path)) => path.is_ident(option),
+            NestedMeta::Meta(Meta::List(list)) => list.path.is_ident(option),
+            _ => false,
+        })
+    })
+}
+
+#[proc_macro_error]
+#[proc_macro_attribute]
+pub fn queries(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
     if !attr.is_empty() {
         emit_error!(
             TokenStream::from(attr),
-            "#[yeter::query] doesn't expect any attributes"
+            "#[yeter::queries] takes no options"
+        );
+    }
+
+    if let Ok(item) = syn::parse::<ItemMod>(item.clone()) {
+        expand_query_mod(item)
+    } else if let Ok(item) = syn::parse::<ItemTrait>(item.clone()) {
+        expand_query_trait(item)
+    } else if let Ok(item) = syn::parse::<ItemImpl>(item.clone()) {
+        expand_query_impl(item)
+    } else {
+        let item = TokenStream::from(item);
+        (quote! { compile_error!("expected trait, impl or mod item"); #item }).into()
+    }
+}
+
+/// Makes every function of a module a query, and adds a `Group` type that lists them
+fn expand_query_mod(mut item: ItemMod) -> proc_macro::TokenStream {
+    let content = match &mut item.content {
+        Some((_, content)) => content,
+        None => abort!(
+            item, "#[yeter::queries] modules must be declared inline";
+            help = "move the content of the module between braces";
+        ),
+    };
+
+    // Functions that are not annotated yet become queries, or input queries if they have no body
+    let mark_query = |attrs: &mut Vec<Attribute>| {
+        let annotated = attrs
+            .iter()
+            .any(|attr| is_yeter_attr(attr, "query") || is_yeter_attr(attr, "input"));
+        if !annotated {
+            attrs.insert(0, parse_quote!(#[::yeter::query]));
+        }
+    };
+    let mut queries = Vec::new();
+    for item in content.iter_mut() {
+        match item {
+            Item::Fn(f) => {
+                mark_query(&mut f.attrs);
+                queries.push((f.sig.clone(), false));
+            }
+            Item::Verbatim(tokens) => {
+                if let Ok(mut f) = syn::parse2::<ForeignItemFn>(tokens.clone()) {
+                    mark_query(&mut f.attrs);
+                    let interface = f
+                        .attrs
+                        .iter()
+                        .any(|attr| has_query_option(attr, "interface"));
+                    queries.push((f.sig.clone(), !interface));
+                    *tokens = quote! { #f };
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let group_queries = queries
+        .iter()
+        .filter_map(|(sig, input)| {
+            let name = &sig.ident;
+            group_query(sig, *input, quote! { #name })
+        })
+        .collect::<Vec<_>>();
+    let group_doc = format!("The queries of the `{}` module", item.ident);
+    content.push(parse_quote! {
+        #[doc = #group_doc]
+        pub enum Group {}
+    });
+    content.push(parse_quote! {
+        impl ::yeter::QueryGroup for Group {
+            fn queries() -> ::std::vec::Vec<::yeter::GroupQuery> {
+                ::std::vec![#(#group_queries),*]
+            }
+        }
+    });
+
+    (quote! { #item }).into()
+}
+
+/// Describes a query of a group, or emits an error if it can't be part of one
+fn group_query(sig: &Signature, input: bool, query_type: TokenStream) -> Option<TokenStream> {
+    if !sig.generics.params.is_empty() {
+        emit_error!(
+            sig.generics, "the queries of a group can't be generic";
+            note = "a group lists its queries at runtime, so they must have a single type";
         );
+        return None;
+    }
+
+    let name = sig.ident.to_string();
+    let kind = Ident::new(if input { "input" } else { "computed" }, Span::call_site());
+    Some(quote! { ::yeter::GroupQuery::#kind::<#query_type>(#name) })
+}
+
+/// Copies an argument with another name, and without the `#[yeter::...]` attributes unless
+/// `keep_key` is true
+fn rename_arg(arg: &FnArg, name: &Ident, keep_key: bool) -> FnArg {
+    let mut arg = match arg {
+        FnArg::Typed(arg) => arg.clone(),
+        FnArg::Receiver(_) => unreachable!("the receiver is never renamed"),
+    };
+    arg.pat = Box::new(parse_quote!(#name));
+    arg.attrs
+        .retain(|attr| keep_key || !(is_yeter_attr(attr, "key") || is_yeter_attr(attr, "no_key")));
+    FnArg::Typed(arg)
+}
+
+/// Makes every method of a trait a query, and implements the trait for every type giving access
+/// to a database
+///
+/// The queries are declared in a hidden module, and `dyn Trait` is the group that lists them.
+fn expand_query_trait(item: ItemTrait) -> proc_macro::TokenStream {
+    if !item.generics.params.is_empty() {
+        abort!(item.generics, "#[yeter::queries] traits can't be generic");
+    }
+
+    let ItemTrait {
+        attrs,
+        vis,
+        ident,
+        supertraits,
+        items,
+        ..
+    } = &item;
+    let module = format_ident!("__yeter_{}", ident);
+    let bodies = format_ident!("__yeter_{}_bodies", ident);
+    let as_dyn = Ident::new("__yeter_as_dyn", Span::call_site());
+    let supertraits = supertraits.iter().collect::<Vec<_>>();
+
+    // The bodies of the queries of a trait with supertraits are called with the database the
+    // queries were called with, as a `&dyn Trait` of any lifetime, so that they can use the
+    // supertraits
+    let (db_type, bodies_type, query_db) = if supertraits.is_empty() {
+        (
+            quote! { ::yeter::Database },
+            quote! { ::yeter::Database },
+            quote! { ::yeter::AsDatabase::as_database(self) },
+        )
+    } else {
+        (
+            quote! { dyn #ident },
+            quote! { dyn #ident + '_ },
+            quote! { #ident::#as_dyn(self) },
+        )
+    };
+
+    let mut methods = Vec::new();
+    let mut queries = Vec::new();
+    let mut body_decls = Vec::new();
+    let mut body_impls = Vec::new();
+    let mut group_queries = Vec::new();
+    for trait_item in items {
+        let method = match trait_item {
+            TraitItem::Method(method) => method,
+            other => {
+                emit_error!(other, "query group traits can only contain methods");
+                continue;
+            }
+        };
+        let TraitItemMethod {
+            attrs,
+            sig,
+            default,
+            ..
+        } = method;
+
+        let receiver = match sig.inputs.first() {
+            Some(FnArg::Receiver(receiver)) => Some(receiver),
+            _ => None,
+        };
+        if !receiver.is_some_and(|r| r.reference.is_some() && r.mutability.is_none()) {
+            emit_error!(
+                sig, "the methods of a query group must take `&self`";
+                note = "`self` is the database the query is called with";
+            );
+            continue;
+        }
+        let (query_attrs, method_attrs): (Vec<_>, Vec<_>) = attrs
+            .iter()
+            .cloned()
+            .partition(|attr| is_yeter_attr(attr, "query") || is_yeter_attr(attr, "input"));
+        let query_attr = query_attrs
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| parse_quote!(#[::yeter::query]));
+
+        let name = &sig.ident;
+        let input = default.is_none() && !has_query_option(&query_attr, "interface");
+        group_queries.extend(group_query(sig, input, quote! { #module::#name }));
+
+        let args = sig.inputs.iter().skip(1).collect::<Vec<_>>();
+        let arg_names = arg_names(args.iter().copied());
+        // Incremental queries receive their previous output, which is not an argument of the
+        // trait method
+        let skipped_args = usize::from(has_query_option(&query_attr, "incremental"));
+        let key_args = args.iter().zip(&arg_names).skip(skipped_args);
+        let key_names = arg_names.iter().skip(skipped_args).collect::<Vec<_>>();
+        let method_args = key_args
+            .clone()
+            .map(|(arg, name)| rename_arg(arg, name, false));
+        let query_args = args
+            .iter()
+            .zip(&arg_names)
+            .map(|(arg, name)| rename_arg(arg, name, true));
+        let body_args = args.iter().map(|arg| {
+            let mut arg = (*arg).clone();
+            if let FnArg::Typed(arg) = &mut arg {
+                arg.attrs
+                    .retain(|attr| !(is_yeter_attr(attr, "key") || is_yeter_attr(attr, "no_key")));
+            }
+            arg
+        });
+
+        let unit_type = build_unit_tuple();
+        let output_type = match &sig.output {
+            ReturnType::Default => &unit_type,
+            ReturnType::Type(_, typ) => typ.as_ref(),
+        };
+        let return_type = if has_query_option(&query_attr, "clone") {
+            quote! { #output_type }
+        } else if has_query_option(&query_attr, "borrow") {
+            quote! { &#output_type }
+        } else {
+            quote! { ::std::rc::Rc<#output_type> }
+        };
+
+        methods.push(quote! {
+            #(#method_attrs)*
+            fn #name(&self, #(#method_args),*) -> #return_type {
+                #module::#name(#query_db, #(#key_names),*)
+            }
+        });
+
+        match default {
+            Some(body) => {
+                let body_name = format_ident!("__yeter_{}", name);
+                let body_args = body_args.collect::<Vec<_>>();
+                body_decls.push(quote! {
+                    fn #body_name(&self, #(#body_args),*) -> #output_type;
+                });
+                body_impls.push(quote! {
+                    fn #body_name(&self, #(#body_args),*) -> #output_type #body
+                });
+                queries.push(quote! {
+                    #query_attr
+                    pub fn #name(db: &#db_type, #(#query_args),*) -> #output_type {
+                        <#db_type as super::#bodies>::#body_name(db, #(#arg_names),*)
+                    }
+                });
+            }
+            None if !input => {
+                queries.push(quote! {
+                    #query_attr
+                    pub fn #name(db: &#db_type, #(#query_args),*) -> #output_type;
+                });
+            }
+            None => {
+                let setter = format_ident!("set_{}", name);
+                let setter_doc = format!("Sets the value of the `{}` input", name);
+                let setter_args = key_args.map(|(arg, name)| rename_arg(arg, name, false));
+                methods.push(quote! {
+                    #[doc = #setter_doc]
+                    fn #setter(&self, #(#setter_args,)* value: #output_type) {
+                        let db = ::yeter::AsDatabase::as_database(self);
+                        db.set::<#module::#name>((#(#key_names,)*), value);
+                    }
+                });
+                queries.push(quote! {
+                    #query_attr
+                    pub fn #name(db: &#db_type, #(#query_args),*) -> #output_type;
+                });
+            }
+        }
     }
 
+    let expanded = quote! {
+        #(#attrs)*
+        #vis trait #ident: ::yeter::AsDatabase #(+ #supertraits)* {
+            #[doc(hidden)]
+            fn #as_dyn(&self) -> &dyn #ident;
+
+            #(#methods)*
+        }
+
+        impl<DB: ::yeter::AsDatabase #(+ #supertraits)*> #ident for DB {
+            fn #as_dyn(&self) -> &dyn #ident {
+                self
+            }
+        }
+
+        impl ::yeter::QueryGroup for dyn #ident {
+            fn queries() -> ::std::vec::Vec<::yeter::GroupQuery> {
+                ::std::vec![#(#group_queries),*]
+            }
+        }
+
+        #[doc(hidden)]
+        #[allow(non_camel_case_types)]
+        trait #bodies {
+            #(#body_decls)*
+        }
+
+        impl #bodies for #bodies_type {
+            #(#body_impls)*
+        }
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        #vis mod #module {
+            use super::*;
+
+            #(#queries)*
+        }
+    };
+
+    set_dummy(expanded.clone());
+    expanded.into()
+}
+
+/// How an argument of a query is part of its cache key
+enum ArgKey {
+    /// The whole argument is part of the key
+    Whole,
+    /// The argument is not part of the key, as with `#[yeter::no_key]`
+    Excluded,
+    /// The hash of an expression is part of the key, as with `#[yeter::key(expr)]`
+    Expr(Box<Expr>),
+}
+
+/// Removes the `#[yeter::no_key]` and `#[yeter::key(...)]` attributes from the arguments of a
+/// query, and returns how each of them is part of the cache key
+fn take_arg_keys(sig: &mut Signature, skipped_args: usize) -> Vec<ArgKey> {
+    sig.inputs
+        .iter_mut()
+        .skip(skipped_args)
+        .map(|arg| {
+            let attrs = match arg {
+                FnArg::Typed(arg) => &mut arg.attrs,
+                FnArg::Receiver(arg) => &mut arg.attrs,
+            };
+            let mut key = ArgKey::Whole;
+            attrs.retain(|attr| {
+                if is_yeter_attr(attr, "no_key") {
+                    key = ArgKey::Excluded;
+                } else if is_yeter_attr(attr, "key") {
+                    match attr.parse_args::<Expr>() {
+                        Ok(expr) => key = ArgKey::Expr(Box::new(expr)),
+                        Err(err) => emit_error!(err.span(), "{}", err),
+                    }
+                } else {
+                    return true;
+                }
+                false
+            });
+            key
+        })
+        .collect()
+}
+
+fn expand_query(options: QueryOptions, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut function_no_impl;
     let mut function_impl;
     let function = {
@@ -157,6 +963,13 @@ pub fn query(
             function_no_impl = f;
             &mut function_no_impl as &mut dyn FunctionItem
         } else if let Ok(f) = syn::parse::<ItemFn>(item.clone()) {
+            if let Some(receiver) = f.sig.receiver() {
+                abort!(
+                    receiver, "query methods must be declared in a #[yeter::queries] impl block";
+                    help = "add #[yeter::queries] to the impl block";
+                    note = "the query types are declared next to the impl block";
+                );
+            }
             function_impl = f;
             &mut function_impl as &mut dyn FunctionItem
         } else {
@@ -166,21 +979,35 @@ pub fn query(
     };
 
     let query_attrs = function.take_attrs();
+    // Incremental queries take their previous output after the database
+    let skipped_args = if options.incremental.is_some() { 2 } else { 1 };
+    let arg_keys = take_arg_keys(function.sig_mut(), skipped_args);
     let fn_args = &function.sig().inputs;
     let query_args = fn_args
         .iter()
-        .skip(1)
+        .skip(skipped_args)
         .map(fn_arg_to_type)
         .cloned()
         .collect::<Vec<_>>();
 
+    // Queries with arguments that are not part of the key can only be called with all of them
+    let custom_key = arg_keys.iter().any(|key| !matches!(key, ArgKey::Whole));
+    if custom_key && (function.has_body() || options.interface.is_some()) {
+        if let Some((ttl, _)) = &options.ttl {
+            emit_error!(
+                ttl, "queries with a time-to-live must have all their arguments in their key";
+                note = "they are re-executed from their key only";
+            );
+        }
+    }
+
     let db_ident_fallback = Ident::new("db", Span::call_site());
-    match fn_args.first() {
+    let db_ident = match fn_args.first() {
         // self, &self, &mut self
         Some(receiver @ FnArg::Receiver(_)) => {
             emit_error!(
                 receiver,
-                "#[yeter::query] can't be used on instance methods";
+                "input queries can't be methods";
                 hint = "did you mean `db: &yeter::Database`?";
             );
 
@@ -208,12 +1035,54 @@ pub fn query(
         }
     };
 
-    let fn_arg_count = fn_args.len() as u32;
-    let query_arg_count = if fn_arg_count == 0 {
-        0
-    } else {
-        fn_arg_count - 1
-    };
+    // Queries can take a reference to any type that gives access to a database
+    let db_type = match fn_args.first() {
+        Some(FnArg::Typed(pat_type)) => match pat_type.ty.as_ref() {
+            Type::Reference(reference) if reference.mutability.is_none() => {
+                Some(reference.elem.as_ref().clone())
+            }
+            typ => {
+                emit_error!(
+                    typ, "the database must be taken by shared reference";
+                    help = "use a type such as `&yeter::Database`";
+                );
+                None
+            }
+        },
+        _ => None,
+    }
+    .unwrap_or_else(|| parse_quote!(::yeter::Database));
+
+    // The functions of queries that take another type than `Database` can't be called from
+    // `Query::run`, so they are called with the database the query function was called with
+    let captured_db = function.has_body() && !is_yeter_database(&db_type);
+    if captured_db {
+        if let Some((ttl, _)) = &options.ttl {
+            emit_error!(
+                ttl, "queries with a time-to-live must take a `&yeter::Database`";
+                note = "they are re-executed with the database only";
+            );
+        }
+    }
+
+    options.check(fn_args.len().saturating_sub(1));
+
+    // Async queries are awaited, so they can't be called from `Query::run`, nor be re-executed
+    // while verifying other queries
+    let is_async = function.sig().asyncness.is_some() && function.has_body();
+    if is_async {
+        let sync_options = [
+            options.incremental.as_ref().map(|_| "incremental"),
+            options.ttl.as_ref().map(|_| "ttl"),
+            options.transparent.as_ref().map(|_| "transparent"),
+        ];
+        for option in sync_options.into_iter().flatten() {
+            emit_error!(
+                function.sig().asyncness, "async queries can't use the `{}` option", option;
+                note = "they can only be executed when they are awaited";
+            );
+        }
+    }
 
     let unit_type;
 
@@ -225,7 +1094,17 @@ pub fn query(
     let generics_args = use_generic_args(generics_params);
     let generics_phantom = generic_args_phantom(&generics_args);
 
-    let input_type = build_type_tuple(query_args.iter().cloned());
+    let input_type =
+        build_type_tuple(
+            query_args
+                .iter()
+                .zip(&arg_keys)
+                .filter_map(|(typ, key)| match key {
+                    ArgKey::Whole => Some(typ.clone()),
+                    ArgKey::Excluded => None,
+                    ArgKey::Expr(_) => Some(parse_quote!(u64)),
+                }),
+        );
     let output_type = match &function.sig().output {
         ReturnType::Default => {
             unit_type = build_unit_tuple();
@@ -234,32 +1113,173 @@ pub fn query(
         ReturnType::Type(_, typ) => typ.as_ref(),
     };
 
-    let calling_arg_names = arg_names(fn_args.iter().skip(1));
+    let calling_arg_names = arg_names(fn_args.iter().skip(skipped_args));
 
     let calling_tuple_args = calling_tuple_args(calling_arg_names.iter().cloned().zip(query_args));
-    let calling_tuple = build_ident_tuple(calling_arg_names.into_iter());
+    let calling_tuple = build_ident_tuple(calling_arg_names.iter().cloned());
+    let input_ident = Ident::new("input", Span::mixed_site());
+
+    // The key is built from the arguments, which are then taken from the key when calling the
+    // query, except for the ones that are not part of it
+    let key_hashes = arg_keys
+        .iter()
+        .zip(&calling_arg_names)
+        .filter_map(|(key, name)| match key {
+            ArgKey::Expr(expr) => {
+                let hash_ident = format_ident!("__yeter_key_{}", name, span = Span::mixed_site());
+                Some((name, hash_ident, expr))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let key_tuple = build_ident_tuple(arg_keys.iter().zip(&calling_arg_names).filter_map(
+        |(key, name)| {
+            match key {
+                ArgKey::Whole => Some(name.clone()),
+                ArgKey::Excluded => None,
+                ArgKey::Expr(_) => key_hashes
+                    .iter()
+                    .find(|(n, _, _)| *n == name)
+                    .map(|(_, hash_ident, _)| hash_ident.clone()),
+            }
+        },
+    ));
+    let key_hashes = key_hashes
+        .iter()
+        .map(|(_, hash_ident, expr)| {
+            quote! { let #hash_ident = ::yeter::key_hash(&(#expr)); }
+        })
+        .collect::<Vec<_>>();
+
+    let mut key_index = 0;
+    let call_args = arg_keys
+        .iter()
+        .zip(&calling_arg_names)
+        .map(|(key, name)| {
+            if !matches!(key, ArgKey::Excluded) {
+                key_index += 1;
+            }
+            match key {
+                ArgKey::Whole => Expr::Field(ExprField {
+                    attrs: Default::default(),
+                    base: Box::new(ident_to_expr(input_ident.clone())),
+                    dot_token: Default::default(),
+                    member: Member::Unnamed(Index {
+                        index: key_index - 1,
+                        span: Span::mixed_site(),
+                    }),
+                }),
+                _ => ident_to_expr(name.clone()),
+            }
+        })
+        .collect::<Punctuated<_, Token![,]>>();
 
     let call_ident_span = Span::call_site().located_at(query_name.span());
     // When Span::def_site is stable, we will be able to properly create hygienic idents
     let call_ident = Ident::new(&format!("__yeter_{query_name}"), call_ident_span);
 
     let to_function_impl = function.to_function_impl(&call_ident, generics_params, output_type);
-    let to_function_call = function.to_function_call(&call_ident, query_arg_count);
+    let query_type = quote! { #query_name::<#generics_args> };
+
+    // The query function takes the database with the name it was given
+    let outer_db_ident = db_ident;
+    let call_db = captured_db.then_some(outer_db_ident);
+    let to_function_call =
+        function.to_function_call(&call_ident, &query_type, &call_args, call_db, &options);
     let to_additional_impl = function.to_additional_impl(
         query_name,
         generics_params,
         &generics_args,
         generics_where,
         output_type,
+        &options,
     );
 
+    // Queries implementing `Query` are re-executed from their key when their tracked entities are
+    // read while they are outdated, which needs their input to be kept in the database
+    let implements_query = !(custom_key || captured_db || is_async);
+    let from_key = implements_query
+        && function.sig().generics.params.is_empty()
+        && !has_lifetimes(function.sig());
+    let run_method = options.run_method(from_key);
+    let ttl_const = options.ttl_const();
+
+    let output_ident = Ident::new("output", Span::mixed_site());
+    let as_database = quote! { ::yeter::AsDatabase::as_database(#outer_db_ident) };
+    let (db_lifetime_param, return_type, return_output) =
+        options.return_output(output_type, &output_ident, &as_database);
+
+    let db_lifetime_generic = db_lifetime_param.as_ref().map(|lt| quote! { #lt, });
+
+    // Queries that can be called from their key implement `Query`, and their function uses it
+    let (query_body, query_impl) = if !implements_query {
+        let run_call = if is_async {
+            quote! {
+                #as_database.run_async::<_, _, #query_type>(#to_function_call, #key_tuple).await
+            }
+        } else {
+            quote! { #as_database.#run_method::<_, #query_type>(#to_function_call, #key_tuple) }
+        };
+        let query_body = quote! {
+            #to_function_impl
+            #(#key_hashes)*
+            let #output_ident = #run_call;
+        };
+        (query_body, None)
+    } else {
+        let query_body = quote! {
+            let #output_ident = <#query_type as ::yeter::Query>::run(#as_database, #calling_tuple);
+        };
+        let query_impl = quote! {
+            impl<#generics_params> ::yeter::Query for #query_name<#generics_args> #generics_where {
+                fn run(db: &::yeter::Database, #input_ident: Self::Input) -> ::std::rc::Rc<Self::Output> {
+                    #to_function_impl
+                    db.#run_method::<_, Self>(#to_function_call, #input_ident)
+                }
+            }
+        };
+        (query_body, Some(query_impl))
+    };
+
+    // The `try_` variant runs the query in the same way, but returns errors instead of panicking
+    let try_query = (!is_async).then(|| {
+        let try_name = format_ident!("try_{}", query_name);
+        let try_doc = format!(
+            "Like `{}`, but returns a `yeter::Error` instead of panicking",
+            query_name
+        );
+        let try_attrs = query_attrs.iter().filter(|attr| !attr.path.is_ident("doc"));
+        let try_run_call = options.try_run_call(
+            from_key,
+            &as_database,
+            &query_type,
+            &to_function_call,
+            &quote! { #key_tuple },
+        );
+        quote! {
+            #[doc = #try_doc]
+            #(#try_attrs)*
+            #query_vis fn #try_name<#db_lifetime_generic #generics_params>(#outer_db_ident: &#db_lifetime_param #db_type, #calling_tuple_args) -> ::std::result::Result<#return_type, ::yeter::Error>
+                #generics_where
+            {
+                ::yeter::Database::catch(#as_database, || {
+                    #to_function_impl
+                    #(#key_hashes)*
+                    let #output_ident = #try_run_call?;
+                    ::std::result::Result::Ok(#return_output)
+                })
+            }
+        }
+    });
+
+    let asyncness = is_async.then(|| quote! { async });
     let expanded = quote! {
         #(#query_attrs)*
-        #query_vis fn #query_name<#generics_params>(db: &::yeter::Database, #calling_tuple_args) -> ::std::rc::Rc<#output_type>
+        #query_vis #asyncness fn #query_name<#db_lifetime_generic #generics_params>(#outer_db_ident: &#db_lifetime_param #db_type, #calling_tuple_args) -> #return_type
             #generics_where
         {
-            #to_function_impl
-            db.run::<_, #query_name::<#generics_args>>(#to_function_call, #calling_tuple)
+            #query_body
+            #return_output
         }
 
         #[allow(non_camel_case_types)]
@@ -271,8 +1291,13 @@ pub fn query(
         impl<#generics_params> ::yeter::QueryDef for #query_name<#generics_args> #generics_where {
             type Input = #input_type;
             type Output = #output_type;
+            #ttl_const
         }
 
+        #query_impl
+
+        #try_query
+
         #to_additional_impl
     };
 
@@ -280,10 +1305,302 @@ pub fn query(
     expanded.into()
 }
 
+/// Replaces `Self` in a type, so that it can be used outside of the impl block it was written in
+fn replace_self(tokens: TokenStream, with: &Type) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Group(group) => {
+                let mut replaced =
+                    Group::new(group.delimiter(), replace_self(group.stream(), with));
+                replaced.set_span(group.span());
+                TokenTree::Group(replaced)
+            }
+            TokenTree::Ident(ident) if ident == "Self" => {
+                TokenTree::Group(Group::new(Delimiter::None, quote! { #with }))
+            }
+            token => token,
+        })
+        .collect()
+}
+
+/// Turns a method taking `self` by value into a query, whose key starts with `self`
+///
+/// Impl blocks can't contain types, so the query definition is returned separately, to be
+/// declared next to the impl block as `__yeter_<Type>_<method>`. The original body is kept in a
+/// hidden method.
+fn expand_query_method(
+    options: QueryOptions,
+    mut item: ItemFn,
+    self_type: &Type,
+    type_name: &Ident,
+) -> (TokenStream, TokenStream) {
+    if let Some(FnArg::Receiver(receiver)) = item.sig.inputs.first() {
+        if receiver.reference.is_some() {
+            emit_error!(
+                receiver, "query methods must take `self` by value";
+                help = "implement `Copy` for this type, and take `self` instead";
+            );
+        }
+    }
+    if !item.sig.generics.params.is_empty() {
+        emit_error!(item.sig.generics, "query methods can't be generic");
+    }
+    if let Some(asyncness) = &item.sig.asyncness {
+        emit_error!(asyncness, "query methods can't be async");
+    }
+    if options.unset_input.is_some() {
+        emit_error!(
+            item.sig, "#[yeter::input] can't be used on a function with a body";
+            help = "use #[yeter::query] instead";
+        );
+    }
+    if let Some(interface) = &options.interface {
+        emit_error!(interface, "interface queries can't have a body");
+    }
+    options.check(item.sig.inputs.len().saturating_sub(2));
+
+    // The receiver and the database come before the arguments, and the previous output of
+    // incremental queries
+    let skipped_args = if options.incremental.is_some() { 3 } else { 2 };
+    let arg_keys = take_arg_keys(&mut item.sig, skipped_args);
+    if arg_keys.iter().any(|key| !matches!(key, ArgKey::Whole)) {
+        emit_error!(
+            item.sig, "all the arguments of query methods are part of their key";
+            note = "#[yeter::no_key] and #[yeter::key] can only be used on functions";
+        );
+    }
+
+    let (outer_db_ident, db_type) = match item.sig.inputs.iter().nth(1) {
+        Some(FnArg::Typed(pat_type)) => match (pat_type.pat.as_ref(), pat_type.ty.as_ref()) {
+            (Pat::Ident(ident), Type::Reference(reference)) if reference.mutability.is_none() => {
+                (ident.ident.clone(), reference.elem.as_ref().clone())
+            }
+            (Pat::Ident(_), typ) => abort!(
+                typ, "the database must be taken by shared reference";
+                help = "use a type such as `&yeter::Database`";
+            ),
+            (pat, _) => abort!(
+                pat, "simple database argument pattern expected";
+                help = "use a simple argument declaration such as `db: &yeter::Database`";
+            ),
+        },
+        _ => abort!(
+            item.sig, "a query method must take a database after `self`";
+            help = "add a `db: &yeter::Database` argument";
+        ),
+    };
+    // Like query functions, methods that take another type than `Database` are called with the
+    // database they were called with
+    let db_ident = Ident::new("db", Span::mixed_site());
+    let call_db = if is_yeter_database(&db_type) {
+        &db_ident
+    } else {
+        if let Some((ttl, _)) = &options.ttl {
+            emit_error!(
+                ttl,
+                "queries with a time-to-live must take a `&yeter::Database`"
+            );
+        }
+        &outer_db_ident
+    };
+
+    let arg_types = item
+        .sig
+        .inputs
+        .iter()
+        .skip(skipped_args)
+        .map(fn_arg_to_type)
+        .cloned()
+        .collect::<Vec<_>>();
+    let key_types = arg_types
+        .iter()
+        .map(|typ| replace_self(quote! { #typ }, self_type))
+        .collect::<Vec<_>>();
+    let arg_names = arg_names(item.sig.inputs.iter().skip(skipped_args));
+    let method_args = calling_tuple_args(arg_names.iter().cloned().zip(arg_types));
+    let method_args = method_args.iter().collect::<Vec<_>>();
+
+    let unit_type = build_unit_tuple();
+    let output_type = match &item.sig.output {
+        ReturnType::Default => &unit_type,
+        ReturnType::Type(_, typ) => typ.as_ref(),
+    };
+    let key_output_type = replace_self(quote! { #output_type }, self_type);
+
+    let ItemFn {
+        attrs, vis, sig, ..
+    } = &item;
+    let name = &sig.ident;
+    let hidden_name = format_ident!("__yeter_{}", name);
+    let mut hidden = item.clone();
+    hidden.attrs.clear();
+    hidden.vis = Visibility::Inherited;
+    hidden.sig.ident = hidden_name.clone();
+
+    let input_ident = Ident::new("input", Span::mixed_site());
+    let previous_ident = options
+        .incremental
+        .as_ref()
+        .map(|_| Ident::new("previous", Span::mixed_site()))
+        .into_iter()
+        .collect::<Vec<_>>();
+    let input_fields = (1..=arg_names.len()).map(Index::from);
+    let call = quote! {
+        Self::#hidden_name(#input_ident.0, #call_db, #(#previous_ident,)* #(#input_ident.#input_fields),*)
+    };
+    let closure = query_closure(call, &options, false);
+
+    let ttl_const = options.ttl_const();
+    let output_ident = Ident::new("output", Span::mixed_site());
+    let as_database = quote! { ::yeter::AsDatabase::as_database(#outer_db_ident) };
+    let (db_lifetime, return_type, return_output) =
+        options.return_output(output_type, &output_ident, &as_database);
+
+    let marker = format_ident!("__yeter_{}_{}", type_name, name);
+    let run_method = options.run_method(false);
+    let key_tuple = quote! { (self, #(#arg_names,)*) };
+    let try_run_call = options.try_run_call(
+        false,
+        &as_database,
+        &quote! { #marker },
+        &closure,
+        &key_tuple,
+    );
+    let try_name = format_ident!("try_{}", name);
+    let try_doc = format!(
+        "Like `{}`, but returns a `yeter::Error` instead of panicking",
+        name
+    );
+    let try_attrs = attrs.iter().filter(|attr| !attr.path.is_ident("doc"));
+
+    let definition = quote! {
+        #[allow(non_camel_case_types)]
+        #[doc(hidden)]
+        #vis enum #marker {}
+
+        impl ::yeter::QueryDef for #marker {
+            type Input = (#self_type, #(#key_types,)*);
+            type Output = #key_output_type;
+            #ttl_const
+        }
+    };
+
+    let methods = quote! {
+        #[doc(hidden)]
+        #[allow(clippy::needless_lifetimes)]
+        #hidden
+
+        #(#attrs)*
+        #vis fn #name<#db_lifetime>(self, #outer_db_ident: &#db_lifetime #db_type, #(#method_args),*) -> #return_type {
+            let #output_ident = #as_database.#run_method::<_, #marker>(#closure, #key_tuple);
+            #return_output
+        }
+
+        #[doc = #try_doc]
+        #(#try_attrs)*
+        #vis fn #try_name<#db_lifetime>(self, #outer_db_ident: &#db_lifetime #db_type, #(#method_args),*) -> ::std::result::Result<#return_type, ::yeter::Error> {
+            ::yeter::Database::catch(#as_database, move || {
+                let #output_ident = #try_run_call?;
+                ::std::result::Result::Ok(#return_output)
+            })
+        }
+    };
+
+    (methods, definition)
+}
+
+/// Turns the methods of an impl block that are annotated with `#[yeter::query]` into queries,
+/// whose definitions are declared next to the impl block
+fn expand_query_impl(mut item: ItemImpl) -> proc_macro::TokenStream {
+    if let Some((_, path, _)) = &item.trait_ {
+        abort!(
+            path, "#[yeter::queries] can't be used on trait impl blocks";
+            help = "declare the query methods in an inherent impl block";
+        );
+    }
+    if !item.generics.params.is_empty() {
+        abort!(
+            item.generics,
+            "#[yeter::queries] impl blocks can't be generic"
+        );
+    }
+    let type_name = match item.self_ty.as_ref() {
+        Type::Path(TypePath { qself: None, path }) => path.segments.last().map(|s| &s.ident),
+        _ => None,
+    };
+    let type_name = match type_name {
+        Some(type_name) => type_name.clone(),
+        None => abort!(
+            item.self_ty, "#[yeter::queries] impl blocks must be for a named type";
+            note = "the query types are named after the type and the method";
+        ),
+    };
+
+    let mut definitions = Vec::new();
+    for impl_item in &mut item.items {
+        let method = match impl_item {
+            ImplItem::Method(method) => method,
+            _ => continue,
+        };
+        let query_attr = match method.attrs.iter().position(|a| is_yeter_attr(a, "query")) {
+            Some(index) => method.attrs.remove(index),
+            None => continue,
+        };
+        if method.sig.receiver().is_none() {
+            emit_error!(
+                method.sig, "query methods must take `self`";
+                help = "declare associated functions that are queries outside of the impl block";
+            );
+            continue;
+        }
+
+        let options = if query_attr.tokens.is_empty() {
+            QueryOptions::default()
+        } else {
+            match query_attr.parse_args::<TokenStream>() {
+                Ok(args) => QueryOptions::parse(args.into()),
+                Err(err) => abort!(err.span(), "{}", err),
+            }
+        };
+        let ImplItemMethod {
+            attrs,
+            vis,
+            sig,
+            block,
+            ..
+        } = method.clone();
+        let item_fn = ItemFn {
+            attrs,
+            vis,
+            sig,
+            block: Box::new(block),
+        };
+        let (methods, definition) =
+            expand_query_method(options, item_fn, &item.self_ty, &type_name);
+        *impl_item = ImplItem::Verbatim(methods);
+        definitions.push(definition);
+    }
+
+    let expanded = quote! {
+        #(#definitions)*
+
+        #item
+    };
+
+    set_dummy(expanded.clone());
+    expanded.into()
+}
+
 trait FunctionItem {
     fn take_attrs(&mut self) -> Vec<Attribute>;
     fn vis(&self) -> &Visibility;
     fn sig(&self) -> &Signature;
+    fn sig_mut(&mut self) -> &mut Signature;
+
+    fn has_body(&self) -> bool {
+        false
+    }
 
     fn to_function_impl(
         &self,
@@ -294,7 +1611,14 @@ trait FunctionItem {
         quote! {}
     }
 
-    fn to_function_call(&self, _call_ident: &Ident, _query_arg_count: u32) -> TokenStream;
+    fn to_function_call(
+        &self,
+        _call_ident: &Ident,
+        _query_type: &TokenStream,
+        _call_args: &Punctuated<Expr, Token![,]>,
+        _call_db: Option<&Ident>,
+        _options: &QueryOptions,
+    ) -> TokenStream;
 
     fn to_additional_impl(
         &self,
@@ -303,22 +1627,70 @@ trait FunctionItem {
         _generics_args: &Punctuated<GenericArgument, Token![,]>,
         _generics_where: &Option<WhereClause>,
         _output_type: &Type,
+        _options: &QueryOptions,
     ) -> TokenStream {
         quote! {}
     }
 }
 
-fn guess_option_inner_type(option: &Type) -> Type {
+/// Checks if a type is `Database`, `yeter::Database` or `::yeter::Database`
+fn is_yeter_database(typ: &Type) -> bool {
+    let path = match typ {
+        Type::Path(TypePath { qself: None, path }) => path,
+        _ => return false,
+    };
+    let segments = path
+        .segments
+        .iter()
+        .map(|seg| &seg.ident)
+        .collect::<Vec<_>>();
+    match segments.as_slice() {
+        [database] => path.leading_colon.is_none() && *database == "Database",
+        [yeter, database] => *yeter == "yeter" && *database == "Database",
+        _ => false,
+    }
+}
+
+/// Checks if tokens contain references or lifetimes other than `'static`
+fn has_non_static_lifetimes(tokens: TokenStream) -> bool {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let is_static =
+        |n: usize| matches!(tokens.get(n), Some(TokenTree::Ident(id)) if id == "static");
+    let is_quote =
+        |n: usize| matches!(tokens.get(n), Some(TokenTree::Punct(p)) if p.as_char() == '\'');
+
+    tokens.iter().enumerate().any(|(n, token)| match token {
+        TokenTree::Group(group) => has_non_static_lifetimes(group.stream()),
+        TokenTree::Punct(p) if p.as_char() == '&' => !(is_quote(n + 1) && is_static(n + 2)),
+        TokenTree::Punct(p) if p.as_char() == '\'' => !is_static(n + 1),
+        _ => false,
+    })
+}
+
+/// Checks if a query has generic lifetimes or arguments that borrow non-static data
+fn has_lifetimes(sig: &Signature) -> bool {
+    let generic_lifetimes = sig.generics.lifetimes().next().is_some();
+    let borrowing_args = sig.inputs.iter().skip(1).any(|arg| {
+        let typ = fn_arg_to_type(arg);
+        has_non_static_lifetimes(quote!(#typ))
+    });
+    generic_lifetimes || borrowing_args
+}
+
+fn check_option_type(option: &Type) {
     if let Type::Path(path) = option {
         match path.path.segments.last() {
             Some(seg) if seg.ident != "Option" => {
-                emit_error!(seg.ident, "expected `Option` type",);
+                emit_error!(
+                    seg.ident, "expected `Option` type";
+                    help = "use #[yeter::input] to declare an input query that returns another type";
+                );
             }
             Some(PathSegment {
                 arguments: PathArguments::AngleBracketed(angle),
                 ..
             }) if angle.args.len() == 1 => match angle.args.first().unwrap() {
-                GenericArgument::Type(t) => return t.clone(),
+                GenericArgument::Type(_) => {}
                 o => {
                     emit_error!(o, "unexpected generic argument for Option type",);
                 }
@@ -331,8 +1703,6 @@ fn guess_option_inner_type(option: &Type) -> Type {
             }
         }
     };
-
-    parse_quote! { Option<()> }
 }
 
 impl FunctionItem for ForeignItemFn {
@@ -348,9 +1718,89 @@ impl FunctionItem for ForeignItemFn {
         &self.sig
     }
 
-    fn to_function_call(&self, _call_ident: &Ident, _query_arg_count: u32) -> TokenStream {
-        quote! {
-            |_db, _input| None
+    fn sig_mut(&mut self) -> &mut Signature {
+        &mut self.sig
+    }
+
+    fn to_function_call(
+        &self,
+        _call_ident: &Ident,
+        query_type: &TokenStream,
+        call_args: &Punctuated<Expr, Token![,]>,
+        _call_db: Option<&Ident>,
+        options: &QueryOptions,
+    ) -> TokenStream {
+        // Interface queries are computed by the installed implementation
+        if let Some(interface) = &options.interface {
+            if let Some(incremental) = &options.incremental {
+                emit_error!(
+                    incremental, "interface queries can't be incremental";
+                    note = "their implementation doesn't receive a previous output";
+                );
+            }
+            let method = &self.sig.ident;
+            let db_ident = Ident::new("db", Span::mixed_site());
+            let call = quote! {
+                ::yeter::Database::implementation::<dyn #interface>(#db_ident)
+                    .#method(#db_ident, #call_args)
+            };
+            return query_closure(call, options, false);
+        }
+
+        if let Some(asyncness) = &self.sig.asyncness {
+            emit_error!(
+                asyncness, "input queries can't be async";
+                note = "their value is set, not computed";
+            );
+        }
+
+        if call_args.iter().any(|arg| !matches!(arg, Expr::Field(_))) {
+            emit_error!(
+                self.sig, "all the arguments of input queries are part of their key";
+                note = "#[yeter::no_key] and #[yeter::key] can only be used on computed queries";
+            );
+        }
+
+        if let Some(volatile) = &options.volatile {
+            emit_error!(
+                volatile, "input queries can't be volatile";
+                note = "their value only changes when it is set";
+            );
+        }
+        if let Some((ttl, _)) = &options.ttl {
+            emit_error!(
+                ttl, "input queries can't have a time-to-live";
+                note = "their value only changes when it is set";
+            );
+        }
+        if let Some(incremental) = &options.incremental {
+            emit_error!(
+                incremental, "input queries can't be incremental";
+                note = "they are not computed";
+            );
+        }
+        if let Some(transparent) = &options.transparent {
+            emit_error!(
+                transparent, "input queries can't be transparent";
+                note = "their value is stored in the database";
+            );
+        }
+        if let Some(cache_err) = &options.cache_err {
+            emit_error!(
+                cache_err, "input queries have no caching policy";
+                note = "their value is kept until it is set again";
+            );
+        }
+
+        // Providers are stored in the database, so they can only be used for 'static inputs
+        if has_lifetimes(&self.sig) {
+            quote! {
+                |_db, input| <#query_type as ::yeter::InputQueryDef>::unset(input)
+            }
+        } else {
+            quote! {
+                |db, input| ::yeter::Database::load_input::<#query_type>(db, input)
+            }
         }
     }
 
@@ -361,13 +1811,43 @@ impl FunctionItem for ForeignItemFn {
         generics_args: &Punctuated<GenericArgument, Token![,]>,
         generics_where: &Option<WhereClause>,
         output_type: &Type,
+        options: &QueryOptions,
     ) -> TokenStream {
-        // Output should be an option; we try to guess what could be inside
-        let output_type = guess_option_inner_type(output_type);
+        if options.interface.is_some() {
+            return quote! {};
+        }
+
+        let input_ident = Ident::new("input", Span::mixed_site());
+        let unset = match options.unset_input.as_ref().unwrap_or(&UnsetInput::None) {
+            UnsetInput::None => {
+                // Output should be an option
+                check_option_type(output_type);
+                quote! { ::std::option::Option::None }
+            }
+            UnsetInput::Default(default) => quote! { #default },
+            UnsetInput::Panic => {
+                let arg_count = self.sig.inputs.len().saturating_sub(1);
+                let args = (0..arg_count)
+                    .map(|n| Ident::new(&format!("arg{n}"), Span::mixed_site()))
+                    .collect::<Vec<_>>();
+                let message = format!(
+                    "input `{query_name}({})` was never set",
+                    vec!["{:?}"; arg_count].join(", ")
+                );
+
+                quote! {
+                    let (#(#args,)*) = #input_ident;
+                    ::std::panic!(#message, #(#args),*)
+                }
+            }
+        };
 
         quote! {
             impl<#generics_params> ::yeter::InputQueryDef for #query_name<#generics_args> #generics_where {
-                type OptionalOutput = #output_type;
+                #[allow(unused_variables)]
+                fn unset(#input_ident: Self::Input) -> Self::Output {
+                    #unset
+                }
             }
         }
     }
@@ -386,6 +1866,14 @@ impl FunctionItem for ItemFn {
         &self.sig
     }
 
+    fn sig_mut(&mut self) -> &mut Signature {
+        &mut self.sig
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
     fn to_function_impl(
         &self,
         call_ident: &Ident,
@@ -401,26 +1889,88 @@ impl FunctionItem for ItemFn {
         }
     }
 
-    fn to_function_call(&self, call_ident: &Ident, query_arg_count: u32) -> TokenStream {
-        let db_ident = Ident::new("db", Span::mixed_site());
-        let input_ident = Ident::new("input", Span::mixed_site());
-        let input_ident_expr = Box::new(ident_to_expr(input_ident.clone()));
-        let calling_args = (0..query_arg_count)
-            .map(|n| {
-                Expr::Field(ExprField {
-                    attrs: Default::default(),
-                    base: input_ident_expr.clone(),
-                    dot_token: Default::default(),
-                    member: Member::Unnamed(Index {
-                        index: n,
-                        span: Span::mixed_site(),
-                    }),
-                })
-            })
-            .collect::<Punctuated<_, Token![,]>>();
+    fn to_function_call(
+        &self,
+        call_ident: &Ident,
+        _query_type: &TokenStream,
+        call_args: &Punctuated<Expr, Token![,]>,
+        call_db: Option<&Ident>,
+        options: &QueryOptions,
+    ) -> TokenStream {
+        if options.unset_input.is_some() {
+            emit_error!(
+                self.sig, "#[yeter::input] can't be used on a function with a body";
+                help = "use #[yeter::query] instead";
+            );
+        }
 
-        quote! {
-            |#db_ident, #input_ident| #call_ident(#db_ident, #calling_args)
+        if let Some(interface) = &options.interface {
+            emit_error!(
+                interface, "interface queries can't have a body";
+                note = "they call the method of the installed implementation";
+            );
         }
+
+        let db_ident = Ident::new("db", Span::mixed_site());
+        let previous_ident = options
+            .incremental
+            .as_ref()
+            .map(|_| Ident::new("previous", Span::mixed_site()))
+            .into_iter()
+            .collect::<Vec<_>>();
+        let call_db = call_db.unwrap_or(&db_ident);
+        let call = quote! { #call_ident(#call_db, #(#previous_ident,)* #call_args) };
+
+        query_closure(call, options, self.sig.asyncness.is_some())
+    }
+}
+
+/// Builds the closure that is given to the database to execute a query, from the expression that
+/// calls its implementation
+///
+/// The closures of async queries return a future, that awaits the call.
+fn query_closure(mut call: TokenStream, options: &QueryOptions, is_async: bool) -> TokenStream {
+    let db_ident = Ident::new("db", Span::mixed_site());
+    let input_ident = Ident::new("input", Span::mixed_site());
+
+    let untracked_read = options.volatile.as_ref().map(|_| {
+        quote! { #db_ident.report_untracked_read(); }
+    });
+
+    let previous_ident = options
+        .incremental
+        .as_ref()
+        .map(|_| Ident::new("previous", Span::mixed_site()))
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    if is_async {
+        call = quote! { #call.await };
+    }
+
+    // Errors are only valid for the current revision, like results of volatile queries
+    if options
+        .cache_err
+        .as_ref()
+        .is_some_and(|cache_err| !cache_err.value)
+    {
+        let output_ident = Ident::new("output", Span::mixed_site());
+        call = quote! {{
+            let #output_ident = #call;
+            if ::std::result::Result::is_err(&#output_ident) {
+                #db_ident.report_untracked_read();
+            }
+            #output_ident
+        }};
+    }
+
+    let body = quote! {
+        #untracked_read
+        #call
+    };
+    if is_async {
+        quote! { |#db_ident, #input_ident| async move { #body } }
+    } else {
+        quote! { |#db_ident, #(#previous_ident,)* #input_ident| { #body } }
     }
 }
@@ -0,0 +1,219 @@
+use crate::{input_hash, Database, Frame, NsTypeId, QueryDef, QueryKey};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+/// An async query call that is being computed
+#[derive(Default)]
+pub(crate) struct InFlight {
+    /// Whether the computation is over, either because it completed or because it was dropped
+    done: Cell<bool>,
+    /// The calls awaiting the computation
+    wakers: RefCell<Vec<Waker>>,
+    /// The in-flight computations this one is awaiting, used to detect cycles
+    awaiting: RefCell<Vec<QueryKey>>,
+}
+
+/// Records that an in-flight computation is awaiting another one, until dropped
+struct Awaiting {
+    in_flight: Rc<InFlight>,
+    key: QueryKey,
+}
+
+impl Awaiting {
+    fn new(in_flight: Rc<InFlight>, key: QueryKey) -> Self {
+        in_flight.awaiting.borrow_mut().push(key);
+        Awaiting { in_flight, key }
+    }
+}
+
+impl Drop for Awaiting {
+    fn drop(&mut self) {
+        let mut awaiting = self.in_flight.awaiting.borrow_mut();
+        if let Some(index) = awaiting.iter().position(|key| *key == self.key) {
+            awaiting.swap_remove(index);
+        }
+    }
+}
+
+/// Waits for an async query call that is computed by another future
+struct Wait(Rc<InFlight>);
+
+impl Future for Wait {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.0.done.get() {
+            return Poll::Ready(());
+        }
+        self.0.wakers.borrow_mut().push(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// Ends an in-flight computation when dropped, waking up the calls awaiting it
+///
+/// If the computation didn't complete (because its future was dropped), one of them computes the
+/// query instead.
+struct Leader<'db> {
+    db: &'db Database,
+    key: QueryKey,
+    in_flight: Rc<InFlight>,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.db.in_flight.borrow_mut().remove(&self.key);
+        self.in_flight.done.set(true);
+        for waker in self.in_flight.wakers.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A future computing a query, whose frame is on the query stack only while it is polled
+///
+/// This keeps the dependencies of async queries apart when their futures are interleaved.
+struct WithFrame<'db, Fut> {
+    db: &'db Database,
+    frame: Option<Frame>,
+    future: Pin<Box<Fut>>,
+}
+
+impl<Fut: Future> Future for WithFrame<'_, Fut> {
+    type Output = (Fut::Output, Frame);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let stack = this.db.stack.get_or_default();
+        stack.borrow_mut().push(this.frame.take().unwrap());
+        let poll = this.future.as_mut().poll(cx);
+        let frame = stack.borrow_mut().pop().unwrap();
+
+        match poll {
+            Poll::Ready(out) => Poll::Ready((out, frame)),
+            Poll::Pending => {
+                this.frame = Some(frame);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Database {
+    /// Checks if the in-flight computation of `from` is awaiting the one of `to`, directly or
+    /// through other in-flight computations
+    fn is_awaiting(&self, from: QueryKey, to: QueryKey) -> bool {
+        let in_flight = self.in_flight.borrow();
+        let mut pending = vec![from];
+        let mut seen = HashSet::new();
+        while let Some(key) = pending.pop() {
+            if key == to {
+                return true;
+            }
+            if let Some(computation) = in_flight.get(&key).filter(|_| seen.insert(key)) {
+                pending.extend(computation.awaiting.borrow().iter().copied());
+            }
+        }
+        false
+    }
+
+    /// Runs an _async query_ (or not if the result is already in the cache)
+    ///
+    /// The dependencies of the query are tracked across `.await` points, even when other queries
+    /// are computed concurrently on the same database. Concurrent calls with the same input share
+    /// a single computation: the first one computes the query, and the other ones wait for its
+    /// output.
+    ///
+    /// Panics if a query ends up in a cyclic computation, including when the cycle goes through
+    /// computations awaited by other futures.
+    ///
+    /// # Executors
+    ///
+    /// The database is not [`Sync`], so the futures of async queries are not [`Send`], and must be
+    /// driven by a single-threaded executor, such as a `LocalSet` or a `LocalPool`:
+    ///
+    /// ```compile_fail
+    /// # use yeter::Database;
+    /// #[yeter::query]
+    /// async fn formatted(db: &Database, name: String) -> String {
+    ///     name
+    /// }
+    ///
+    /// fn spawn(task: impl std::future::Future + Send) {}
+    ///
+    /// let db = Database::new();
+    /// spawn(formatted(&db, "main.rs".into()));
+    /// ```
+    pub async fn run_async<'db, F, Fut, Q>(&'db self, f: F, i: Q::Input) -> Rc<Q::Output>
+    where
+        F: FnOnce(&'db Database, Q::Input) -> Fut,
+        Fut: Future<Output = Q::Output>,
+        Q: QueryDef,
+        Q::Input: Hash,
+        Q::Output: 'static,
+    {
+        let key = (NsTypeId::of::<Q>(), input_hash(&i));
+
+        let caller = {
+            let mut stack = self.stack.get_or_default().borrow_mut();
+            if stack.iter().any(|frame| frame.key == Some(key)) {
+                panic!("cyclic computation of an async query");
+            }
+
+            stack.last_mut().and_then(|frame| {
+                frame.dependencies.push(key);
+                frame.key
+            })
+        };
+
+        // The in-flight computation of the calling query awaits this call until it returns
+        let caller_in_flight =
+            caller.and_then(|caller| self.in_flight.borrow().get(&caller).cloned());
+        let _awaiting = caller_in_flight.map(|in_flight| Awaiting::new(in_flight, key));
+
+        loop {
+            if let Some((_, cc)) = self.verify(key, true) {
+                return cc
+                    .value
+                    .clone()
+                    .downcast()
+                    .expect("Cached computation was not of the correct type");
+            }
+
+            let in_flight = self.in_flight.borrow().get(&key).cloned();
+            match in_flight {
+                Some(in_flight) => {
+                    if caller.is_some_and(|caller| self.is_awaiting(key, caller)) {
+                        panic!("cyclic computation of an async query");
+                    }
+                    Wait(in_flight).await
+                }
+                None => break,
+            }
+        }
+
+        let in_flight = Rc::<InFlight>::default();
+        self.in_flight.borrow_mut().insert(key, in_flight.clone());
+        let _leader = Leader {
+            db: self,
+            key,
+            in_flight,
+        };
+
+        let revision = self.revision.get();
+        let future = WithFrame {
+            db: self,
+            frame: Some(self.new_frame(key)),
+            future: Box::pin(f(self, i)),
+        };
+        let (out, frame) = future.await;
+        self.save::<Q>(key, revision, frame, out, None, None)
+    }
+}
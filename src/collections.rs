@@ -0,0 +1,309 @@
+use crate::{Database, Id, InputQueryDef, QueryDef};
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+/// A map input, whose entries are tracked separately
+///
+/// A query that reads one entry with [`IncrementalMap::get`] only depends on that entry, so
+/// inserting or removing other ones doesn't invalidate it. A query that reads the
+/// [keys][IncrementalMap::keys] or the [length][IncrementalMap::len] of the map depends on the
+/// set of keys, but not on the values.
+///
+/// The map itself is only a cheap copyable handle: its content is stored in the database it was
+/// created with.
+pub struct IncrementalMap<K, V>(Id<MapKeys<K, V>>);
+
+/// A vector input, whose elements are tracked separately
+///
+/// A query that reads one element with [`IncrementalVec::get`] only depends on that element.
+/// A query that reads the [length][IncrementalVec::len] of the vector depends on it, but not
+/// on the elements.
+///
+/// Like [`IncrementalMap`], it is a cheap copyable handle to content stored in the database.
+pub struct IncrementalVec<T>(Id<VecLen<T>>);
+
+/// The query that lists the keys of a map, in insertion order
+struct MapKeys<K, V>(PhantomData<(K, V)>);
+
+/// The input query that holds the number of keys ever inserted in a map, including removed ones
+struct MapSlots<K, V>(PhantomData<(K, V)>);
+
+/// The input query that holds the key inserted in a slot of a map, unless it was removed
+struct MapSlot<K, V>(PhantomData<(K, V)>);
+
+/// The input query that holds the slot and value of a map entry, if there is one
+struct MapEntry<K, V>(PhantomData<(K, V)>);
+
+/// The input query that holds the length of a vector
+struct VecLen<T>(PhantomData<T>);
+
+/// The input query that holds an element of a vector
+struct VecElement<T>(PhantomData<T>);
+
+impl<K, V> QueryDef for MapKeys<K, V> {
+    type Input = (IncrementalMap<K, V>,);
+    type Output = Vec<K>;
+}
+
+impl<K, V> QueryDef for MapSlots<K, V> {
+    type Input = (IncrementalMap<K, V>,);
+    type Output = usize;
+}
+
+impl<K, V> InputQueryDef for MapSlots<K, V> {
+    fn unset(_: Self::Input) -> usize {
+        0
+    }
+}
+
+impl<K, V> QueryDef for MapSlot<K, V> {
+    type Input = (IncrementalMap<K, V>, usize);
+    type Output = Option<K>;
+}
+
+impl<K, V> InputQueryDef for MapSlot<K, V> {
+    fn unset(_: Self::Input) -> Option<K> {
+        None
+    }
+}
+
+impl<K, V> QueryDef for MapEntry<K, V> {
+    type Input = (IncrementalMap<K, V>, K);
+    type Output = Option<(usize, Rc<V>)>;
+}
+
+impl<K, V> InputQueryDef for MapEntry<K, V> {
+    fn unset(_: Self::Input) -> Option<(usize, Rc<V>)> {
+        None
+    }
+}
+
+impl<T> QueryDef for VecLen<T> {
+    type Input = (IncrementalVec<T>,);
+    type Output = usize;
+}
+
+impl<T> InputQueryDef for VecLen<T> {
+    fn unset(_: Self::Input) -> usize {
+        0
+    }
+}
+
+impl<T> QueryDef for VecElement<T> {
+    type Input = (IncrementalVec<T>, usize);
+    type Output = Option<Rc<T>>;
+}
+
+impl<T> InputQueryDef for VecElement<T> {
+    fn unset(_: Self::Input) -> Option<Rc<T>> {
+        None
+    }
+}
+
+impl Database {
+    /// Reads an input, recording a dependency on it if called from a query
+    fn read_input<Q>(&self, input: Q::Input) -> Rc<Q::Output>
+    where
+        Q: InputQueryDef,
+        Q::Input: Hash + 'static,
+        Q::Output: 'static,
+    {
+        self.run::<_, Q>(Database::load_input::<Q>, input)
+    }
+
+    /// Sets an input, unless it already has an equal value
+    fn update_input<Q>(&self, input: Q::Input, output: Q::Output)
+    where
+        Q: InputQueryDef,
+        Q::Input: Hash + Clone + 'static,
+        Q::Output: PartialEq + 'static,
+    {
+        let current = self.untracked(|| self.read_input::<Q>(input.clone()));
+        if *current != output {
+            self.set::<Q>(input, output);
+        }
+    }
+}
+
+impl<K, V> IncrementalMap<K, V>
+where
+    K: Hash + Eq + Clone + 'static,
+    V: PartialEq + 'static,
+{
+    /// Creates an empty map in a database
+    pub fn new(db: &Database) -> Self {
+        IncrementalMap(db.new_input_id())
+    }
+
+    /// Returns the value associated with a key, if there is one
+    pub fn get(self, db: &Database, key: K) -> Option<Rc<V>> {
+        let entry = db.read_input::<MapEntry<K, V>>((self, key));
+        Option::as_ref(&entry).map(|(_, value)| value.clone())
+    }
+
+    /// Returns the keys of the map, in insertion order
+    ///
+    /// The list is only built again when it is read after keys were inserted or removed.
+    pub fn keys(self, db: &Database) -> Rc<Vec<K>> {
+        let f = |db: &Database, (map,): (Self,)| {
+            let slots = *db.read_input::<MapSlots<K, V>>((map,));
+            (0..slots)
+                .filter_map(|slot| Option::clone(&db.read_input::<MapSlot<K, V>>((map, slot))))
+                .collect()
+        };
+        db.run::<_, MapKeys<K, V>>(f, (self,))
+    }
+
+    /// Returns the number of entries in the map
+    pub fn len(self, db: &Database) -> usize {
+        self.keys(db).len()
+    }
+
+    /// Returns whether the map has no entries
+    pub fn is_empty(self, db: &Database) -> bool {
+        self.keys(db).is_empty()
+    }
+
+    /// Associates a value with a key
+    ///
+    /// Queries that read other entries are not invalidated, and neither are the ones that depend
+    /// on the keys of the map if the key was already there.
+    pub fn insert(self, db: &Database, key: K, value: V) {
+        let entry = db.untracked(|| db.read_input::<MapEntry<K, V>>((self, key.clone())));
+        let slot = match &*entry {
+            Some((slot, _)) => *slot,
+            None => {
+                let slot = *db.untracked(|| db.read_input::<MapSlots<K, V>>((self,)));
+                db.set::<MapSlot<K, V>>((self, slot), Some(key.clone()));
+                db.set::<MapSlots<K, V>>((self,), slot + 1);
+                slot
+            }
+        };
+        db.update_input::<MapEntry<K, V>>((self, key), Some((slot, Rc::new(value))));
+    }
+
+    /// Removes the entry of a key, if there is one
+    pub fn remove(self, db: &Database, key: K) {
+        let entry = db.untracked(|| db.read_input::<MapEntry<K, V>>((self, key.clone())));
+        if let Some((slot, _)) = &*entry {
+            db.set::<MapSlot<K, V>>((self, *slot), None);
+            db.set::<MapEntry<K, V>>((self, key), None);
+        }
+    }
+}
+
+impl<T> IncrementalVec<T>
+where
+    T: PartialEq + 'static,
+{
+    /// Creates an empty vector in a database
+    pub fn new(db: &Database) -> Self {
+        IncrementalVec(db.new_input_id())
+    }
+
+    /// Returns the element at an index, if it is in bounds
+    pub fn get(self, db: &Database, index: usize) -> Option<Rc<T>> {
+        Option::clone(&db.read_input::<VecElement<T>>((self, index)))
+    }
+
+    /// Returns the number of elements in the vector
+    pub fn len(self, db: &Database) -> usize {
+        *db.read_input::<VecLen<T>>((self,))
+    }
+
+    /// Returns whether the vector has no elements
+    pub fn is_empty(self, db: &Database) -> bool {
+        self.len(db) == 0
+    }
+
+    /// Appends an element to the vector
+    pub fn push(self, db: &Database, value: T) {
+        let len = db.untracked(|| self.len(db));
+        db.set::<VecElement<T>>((self, len), Some(Rc::new(value)));
+        db.set::<VecLen<T>>((self,), len + 1);
+    }
+
+    /// Removes the last element of the vector, if there is one
+    pub fn pop(self, db: &Database) {
+        let len = db.untracked(|| self.len(db));
+        if let Some(len) = len.checked_sub(1) {
+            db.set::<VecLen<T>>((self,), len);
+            db.set::<VecElement<T>>((self, len), None);
+        }
+    }
+
+    /// Replaces the element at an index
+    ///
+    /// Only the queries that read this element are invalidated. Panics if the index is out of
+    /// bounds.
+    pub fn set(self, db: &Database, index: usize, value: T) {
+        let len = db.untracked(|| self.len(db));
+        assert!(
+            index < len,
+            "index {} out of bounds (length {})",
+            index,
+            len
+        );
+        db.update_input::<VecElement<T>>((self, index), Some(Rc::new(value)));
+    }
+}
+
+impl<K, V> Clone for IncrementalMap<K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for IncrementalMap<K, V> {}
+
+impl<K, V> PartialEq for IncrementalMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K, V> Eq for IncrementalMap<K, V> {}
+
+impl<K, V> Hash for IncrementalMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<K, V> fmt::Debug for IncrementalMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IncrementalMap").field(&self.0).finish()
+    }
+}
+
+impl<T> Clone for IncrementalVec<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for IncrementalVec<T> {}
+
+impl<T> PartialEq for IncrementalVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T> Eq for IncrementalVec<T> {}
+
+impl<T> Hash for IncrementalVec<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for IncrementalVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("IncrementalVec").field(&self.0).finish()
+    }
+}
@@ -0,0 +1,159 @@
+use crate::{CycleError, Database};
+use std::{
+    any::Any,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// An error returned by the `try_` variants of queries, instead of panicking
+///
+/// Queries that return a `Result<T, yeter::Error>` can propagate it from the queries they call
+/// with `?`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// A query ended up in a cyclic computation
+    Cycle,
+    /// The computations were [cancelled][Database::cancel]
+    Cancelled,
+    /// A query panicked, with this message
+    Panicked(String),
+}
+
+impl From<CycleError> for Error {
+    fn from(_: CycleError) -> Self {
+        Error::Cycle
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Cycle => write!(f, "cyclic computation of a query"),
+            Error::Cancelled => write!(f, "query computations were cancelled"),
+            Error::Panicked(message) => write!(f, "query panicked: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A handle to [cancel][Database::cancel] the computations of a database from another thread,
+/// returned by [`Database::cancel_handle`]
+#[derive(Clone, Debug)]
+pub struct CancelHandle(Arc<AtomicUsize>);
+
+impl CancelHandle {
+    /// Cancels the computations of the current revision of the database
+    ///
+    /// The database notices it before executing its next query. Requests that it didn't notice
+    /// before a new revision started are dropped.
+    pub fn cancel(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// The payload of the unwinding that interrupts cancelled computations
+struct Cancelled;
+
+/// Finds the message of a panic, if it has one
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Box<dyn Any>".to_string(),
+        },
+    }
+}
+
+impl Database {
+    /// Cancels the computations of the current revision
+    ///
+    /// The `try_` variants of queries return [`Error::Cancelled`] until the next revision starts.
+    /// Queries that are running in one of them are interrupted before executing their next query,
+    /// even if they call it with a function that is not a `try_` variant. Other query functions
+    /// are not affected.
+    pub fn cancel(&self) {
+        self.cancelled_at.set(Some(self.revision.get()));
+    }
+
+    /// Returns a handle to cancel the computations of this database from another thread
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle(self.cancel_requests.clone())
+    }
+
+    /// Checks if the computations of the current revision were cancelled
+    fn is_cancelled(&self) -> bool {
+        if self.cancel_requests.swap(0, Ordering::Relaxed) > 0 {
+            self.cancel();
+        }
+        self.cancelled_at.get() == Some(self.revision.get())
+    }
+
+    /// Interrupts the computations up to the innermost [`Database::catch`] call if they were
+    /// cancelled
+    ///
+    /// This unwinds without calling the panic hook, so nothing is printed.
+    pub(crate) fn unwind_if_cancelled(&self) {
+        if self.catching.get() > 0 && self.is_cancelled() {
+            panic::resume_unwind(Box::new(Cancelled));
+        }
+    }
+
+    /// Calls a function that runs queries, turning panics into errors
+    ///
+    /// This is what the `try_` variants of queries do. It returns [`Error::Cancelled`] without
+    /// calling `f` if the computations were [cancelled][Database::cancel], or if they were
+    /// cancelled while `f` was running, and [`Error::Panicked`] if `f` panicked. Panics are still
+    /// reported by the panic hook, but cancellations are not.
+    ///
+    /// When called from a query, errors are only valid for the current revision: the query is
+    /// re-executed in the next one, like if it [read untracked state][Database::report_untracked_read].
+    ///
+    /// # Unwind safety
+    ///
+    /// The queries interrupted by a panic are removed from the query stack, along with the
+    /// [tracked entities][Database::new_tracked] and the effects they produced, and their cache
+    /// items are left as they were, since they are only saved once a query completes. Queries
+    /// that completed before the panic keep their results, which don't depend on the interrupted
+    /// ones. Other state captured by `f` is not restored.
+    ///
+    /// Some state is lost with the interrupted queries, though:
+    ///
+    /// - An [incremental query][Database::run_incremental] takes its previous output out of its
+    ///   cache item before being re-executed, so if it is interrupted, its next execution gets
+    ///   `None` and starts from scratch.
+    /// - The effects [produced outside of a query][Database::do_effect] are attached to the next
+    ///   query that starts being computed, so they are dropped if it is interrupted.
+    pub fn catch<R>(&self, f: impl FnOnce() -> Result<R, Error>) -> Result<R, Error> {
+        let result = if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            let depth = self.stack.get_or_default().borrow().len();
+            self.catching.set(self.catching.get() + 1);
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            self.catching.set(self.catching.get() - 1);
+            result.unwrap_or_else(|payload| {
+                let mut stack = self.stack.get_or_default().borrow_mut();
+                stack.truncate(depth);
+                let active = |key| stack.iter().any(|frame| frame.key == Some(key));
+                self.created
+                    .borrow_mut()
+                    .retain(|(creator, _, _)| active(*creator));
+                match payload.downcast::<Cancelled>() {
+                    Ok(_) => Err(Error::Cancelled),
+                    Err(payload) => Err(Error::Panicked(panic_message(payload))),
+                }
+            })
+        };
+
+        if result.is_err() {
+            self.report_untracked_read();
+        }
+        result
+    }
+}
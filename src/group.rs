@@ -0,0 +1,112 @@
+use crate::{CachedComputation, Database, NsTypeId, QueryDef};
+use std::rc::Rc;
+
+/// A set of queries declared together with [`#[yeter::queries]`][crate::queries]
+///
+/// Its queries can be enumerated at runtime, to inspect or drop their cache items all at once.
+pub trait QueryGroup {
+    /// The queries of the group, in declaration order
+    fn queries() -> Vec<GroupQuery>;
+}
+
+/// A query of a [`QueryGroup`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GroupQuery {
+    name: &'static str,
+    query: NsTypeId,
+    input: bool,
+}
+
+impl GroupQuery {
+    /// Describes a computed query of a group
+    pub fn computed<Q: QueryDef>(name: &'static str) -> Self {
+        GroupQuery {
+            name,
+            query: NsTypeId::of::<Q>(),
+            input: false,
+        }
+    }
+
+    /// Describes an input query of a group
+    pub fn input<Q: QueryDef>(name: &'static str) -> Self {
+        GroupQuery {
+            name,
+            query: NsTypeId::of::<Q>(),
+            input: true,
+        }
+    }
+
+    /// The name of the query
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Whether this is an input query, whose values are set instead of computed
+    pub fn is_input(&self) -> bool {
+        self.input
+    }
+}
+
+/// Statistics about the cache of a query, returned by [`Database::group_stats`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct QueryStats {
+    /// The name of the query
+    pub name: &'static str,
+    /// The number of cache items of the query, in this database and in its base
+    pub entries: usize,
+}
+
+impl Database {
+    /// Returns statistics about the caches of the queries of a group
+    pub fn group_stats<G: QueryGroup + ?Sized>(&self) -> Vec<QueryStats> {
+        let items = self.visible_items();
+        G::queries()
+            .into_iter()
+            .map(|q| QueryStats {
+                name: q.name,
+                entries: items.iter().filter(|((id, _), _)| *id == q.query).count(),
+            })
+            .collect()
+    }
+
+    /// Invalidates the results of the computed queries of a group
+    ///
+    /// They are re-executed on their next access, without early cutoff, and incremental queries
+    /// don't receive their previous output. Inputs of the group keep their values.
+    pub fn invalidate_group<G: QueryGroup + ?Sized>(&self) {
+        let computed = G::queries()
+            .into_iter()
+            .filter(|q| !q.input)
+            .map(|q| q.query)
+            .collect::<Vec<_>>();
+        self.new_revision();
+
+        // Items of the base database are shadowed, since they are shared with its other overlays
+        let items = self.visible_items();
+        let mut caches = self.caches.write().unwrap();
+        for ((q, input_hash), cc) in items {
+            if computed.contains(&q) {
+                let cc = CachedComputation {
+                    redefined: true,
+                    ..CachedComputation::clone(&cc)
+                };
+                let cache = Rc::make_mut(caches.entry(q).or_default());
+                cache.insert(input_hash, Rc::new(cc));
+            }
+        }
+    }
+
+    /// Drops the cache items of all the queries of a group from this database, to free memory
+    ///
+    /// Computed queries are re-executed on their next access, and inputs are unset. Cache items
+    /// of the base database, if this one is an overlay, are kept.
+    pub fn clear_group<G: QueryGroup + ?Sized>(&self) {
+        let queries = G::queries();
+        self.new_revision();
+
+        let mut caches = self.caches.write().unwrap();
+        for q in queries {
+            caches.remove(&q.query);
+        }
+    }
+}
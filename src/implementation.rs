@@ -0,0 +1,38 @@
+use crate::{Database, InputQueryDef, QueryDef};
+use std::{any::type_name, marker::PhantomData, rc::Rc};
+
+/// The input query that holds the installed implementation of the interface `I`
+struct Implementation<I: ?Sized>(PhantomData<fn() -> Rc<I>>);
+
+impl<I: ?Sized> QueryDef for Implementation<I> {
+    type Input = ();
+    type Output = Rc<I>;
+}
+
+impl<I: ?Sized> InputQueryDef for Implementation<I> {
+    fn unset(_: ()) -> Rc<I> {
+        panic!("no implementation of `{}` was installed", type_name::<I>())
+    }
+}
+
+impl Database {
+    /// Installs the implementation of an interface, typically a trait object type
+    ///
+    /// The queries that used the previous implementation are invalidated. Queries declared with
+    /// `#[yeter::query(interface(Trait))]` call the method of the installed `dyn Trait` that has
+    /// their name.
+    pub fn install<I: ?Sized + 'static>(&self, implementation: Box<I>) {
+        self.set::<Implementation<I>>((), Rc::from(implementation));
+    }
+
+    /// Returns the installed implementation of an interface
+    ///
+    /// When called from a query, it depends on the implementation, and is re-executed when
+    /// another one is [installed][Database::install].
+    ///
+    /// Panics if no implementation of the interface was installed.
+    pub fn implementation<I: ?Sized + 'static>(&self) -> Rc<I> {
+        let load = Database::load_input::<Implementation<I>>;
+        Rc::clone(&self.run::<_, Implementation<I>>(load, ()))
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{cache_item_mut, input_hash, CycleError, Database, NsTypeId, QueryDef, QueryKey};
+use std::{collections::HashSet, hash::Hash, mem, rc::Rc};
+
+/// The previous output of an [incremental query][Database::run_incremental]
+///
+/// It lets the query patch its previous output instead of computing a new one from scratch,
+/// by only looking at the dependencies that [changed][Previous::changed].
+pub struct Previous<T> {
+    /// The output of the previous execution of the query
+    pub output: Rc<T>,
+    /// The dependencies of the previous execution that are known to be unchanged
+    unchanged: HashSet<QueryKey>,
+}
+
+impl<T> Previous<T> {
+    /// Returns whether a query call may have changed since the previous execution
+    ///
+    /// Calls that the previous execution didn't depend on are reported as changed, since the
+    /// previous output doesn't account for them.
+    pub fn changed<'input, Q>(&self, input: &Q::Input) -> bool
+    where
+        Q: QueryDef,
+        Q::Input: Hash + 'input,
+    {
+        let key = (NsTypeId::of::<Q>(), input_hash(input));
+        !self.unchanged.contains(&key)
+    }
+
+    /// Takes the previous output, cloning it if it is still shared
+    ///
+    /// The output is moved out of the cache before the query is re-executed, so it is only shared
+    /// if it was kept by a caller of the query, or by a [fork][Database::fork] of the database.
+    pub fn into_output(self) -> T
+    where
+        T: Clone,
+    {
+        Rc::unwrap_or_clone(self.output)
+    }
+}
+
+impl Database {
+    /// Runs an _incremental query_, which receives its previous output when it is re-executed
+    ///
+    /// The previous output is `None` the first time the query is executed. Otherwise, the query
+    /// can use it to patch its output, using [`Previous::changed`] to know which of its
+    /// dependencies changed. The dependencies of the previous execution that didn't change are
+    /// kept as dependencies of the new one, so the query only has to read the ones that changed.
+    ///
+    /// Panics if a query ends up in a cyclic computation
+    pub fn run_incremental<F, Q>(&self, f: F, i: Q::Input) -> Rc<Q::Output>
+    where
+        F: FnOnce(&Database, Option<Previous<Q::Output>>, Q::Input) -> Q::Output,
+        Q: QueryDef,
+        Q::Input: Hash,
+        Q::Output: 'static,
+    {
+        self.try_run_incremental::<F, Q>(f, i).unwrap()
+    }
+
+    /// Tries to run an [incremental query][Database::run_incremental]
+    pub fn try_run_incremental<F, Q>(&self, f: F, i: Q::Input) -> Result<Rc<Q::Output>, CycleError>
+    where
+        F: FnOnce(&Database, Option<Previous<Q::Output>>, Q::Input) -> Q::Output,
+        Q: QueryDef,
+        Q::Input: Hash,
+        Q::Output: 'static,
+    {
+        let key = (NsTypeId::of::<Q>(), input_hash(&i));
+        let f = |db: &Database, i| f(db, db.previous(key), i);
+        self.execute::<_, Q>(f, i, |_, _| None, None)
+    }
+
+    /// Finds the previous output of a query call that is about to be re-executed
+    ///
+    /// The unchanged dependencies of the previous execution are recorded as dependencies of the
+    /// current query. If the cache item is in this database, its output is moved out of it, and
+    /// the item is marked as redefined so that it is not used anymore.
+    fn previous<T: 'static>(&self, key: QueryKey) -> Option<Previous<T>> {
+        let (depth, cc) = self.find_item(key)?;
+        if cc.redefined || !cc.value.is::<T>() {
+            return None;
+        }
+
+        let mut unchanged = HashSet::new();
+        let carried = cc
+            .dependencies
+            .iter()
+            .copied()
+            .filter(|&dep| {
+                let dep_cc = self.verify(dep, true).or_else(|| self.refresh(dep));
+                dep_cc.is_some_and(|(_, dep_cc)| dep_cc.changed_at <= cc.verified_at)
+            })
+            .filter(|&dep| unchanged.insert(dep))
+            .collect::<Vec<_>>();
+        if let Some(frame) = self.stack.get_or_default().borrow_mut().last_mut() {
+            frame.dependencies.extend(carried);
+        }
+
+        // Items of a base database are shared with its other overlays, so they are left untouched
+        let output = if depth == 0 {
+            drop(cc);
+            let mut caches = self.caches.write().unwrap();
+            let cc = cache_item_mut(&mut caches, key)?;
+            cc.redefined = true;
+            mem::replace(&mut cc.value, Rc::new(()))
+        } else {
+            cc.value.clone()
+        };
+
+        Some(Previous {
+            output: output.downcast().ok()?,
+            unchanged,
+        })
+    }
+}
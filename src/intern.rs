@@ -0,0 +1,153 @@
+use crate::Database;
+use std::{
+    any::{Any, TypeId},
+    cmp::Ordering,
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    rc::Rc,
+    sync::RwLock,
+};
+
+/// An identifier for a value interned with [`Database::intern`]
+///
+/// It is cheap to copy, compare and hash, which makes it a good query input in place of large
+/// values like strings or paths. The value it stands for is retrieved with [`Database::lookup`].
+pub struct Id<T> {
+    index: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// The interning tables of a database, one per interned type
+///
+/// They are shared by a database and all of its forks and overlays. Values are never
+/// removed from them, so the ids given by any of these databases are valid in all the others.
+#[derive(Default)]
+pub(crate) struct Interner {
+    tables: RwLock<HashMap<TypeId, Box<dyn Any>>>,
+    /// Number of ids given by [`Database::new_input_id`], for each type
+    input_ids: RwLock<HashMap<TypeId, u32>>,
+}
+
+/// The interning table of values of type `T`
+struct Table<T> {
+    ids: HashMap<Rc<T>, Id<T>>,
+    values: Vec<Rc<T>>,
+}
+
+impl Database {
+    /// Interns a value, returning its id
+    ///
+    /// Interning equal values gives the same id. Interning is not tracked as a dependency of the
+    /// current query: an id stays valid, and always refers to the same value, for the whole life of
+    /// the database.
+    pub fn intern<T>(&self, value: T) -> Id<T>
+    where
+        T: Hash + Eq + 'static,
+    {
+        let mut tables = self.interner.tables.write().unwrap();
+        let table = tables
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| {
+                Box::new(Table::<T> {
+                    ids: HashMap::new(),
+                    values: Vec::new(),
+                })
+            })
+            .downcast_mut::<Table<T>>()
+            .unwrap();
+
+        if let Some(id) = table.ids.get(&value) {
+            return *id;
+        }
+
+        let index = u32::try_from(table.values.len()).expect("too many interned values");
+        let id = Id::new(index);
+        let value = Rc::new(value);
+        table.values.push(value.clone());
+        table.ids.insert(value, id);
+        id
+    }
+
+    /// Returns the value an id was [interned][Database::intern] from
+    ///
+    /// Panics if the id was not given by this database, or one sharing its interning tables (its
+    /// forks and overlays).
+    pub fn lookup<T>(&self, id: Id<T>) -> Rc<T>
+    where
+        T: 'static,
+    {
+        let tables = self.interner.tables.read().unwrap();
+        tables
+            .get(&TypeId::of::<T>())
+            .and_then(|table| table.downcast_ref::<Table<T>>())
+            .and_then(|table| table.values.get(id.index as usize))
+            .expect("unknown interned id")
+            .clone()
+    }
+
+    /// Returns a new id for an input struct, distinct from all the ones given before
+    ///
+    /// These ids don't stand for an interned value and can't be [looked up][Database::lookup].
+    #[doc(hidden)]
+    pub fn new_input_id<T>(&self) -> Id<T>
+    where
+        T: 'static,
+    {
+        let mut input_ids = self.interner.input_ids.write().unwrap();
+        let count = input_ids.entry(TypeId::of::<T>()).or_default();
+        let id = Id::new(*count);
+        *count = count.checked_add(1).expect("too many inputs");
+        id
+    }
+}
+
+impl<T> Id<T> {
+    fn new(index: u32) -> Self {
+        Id {
+            index,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Id<T> {}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> PartialOrd for Id<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Id<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.index.cmp(&other.index)
+    }
+}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Id<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Id({})", self.index)
+    }
+}
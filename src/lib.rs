@@ -1,18 +1,40 @@
+mod asynchronous;
+mod collections;
+mod error;
+mod group;
+mod implementation;
+mod incremental;
+mod intern;
 mod ns_type_id;
+mod projection;
+mod tracked;
 
+use asynchronous::InFlight;
+pub use collections::{IncrementalMap, IncrementalVec};
+pub use error::{CancelHandle, Error};
+pub use group::{GroupQuery, QueryGroup, QueryStats};
+pub use incremental::Previous;
+pub use intern::Id;
+use intern::Interner;
 use ns_type_id::NsTypeId;
 use std::{
     any::Any,
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::{hash_map::DefaultHasher, HashMap},
     hash::{Hash, Hasher},
-    rc::Rc,
-    sync::RwLock,
+    marker::PhantomData,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
 };
+pub use tracked::Tracked;
 
 use thread_local::ThreadLocal;
 
-type AnyMap = anymap2::Map<dyn anymap2::any::Any + Send>;
+type AnyMap = anymap2::Map<dyn anymap2::any::CloneAnySend + Send>;
 
 /// A query definition
 ///
@@ -22,19 +44,154 @@ pub trait QueryDef {
     type Input;
     /// Output type
     type Output;
+
+    /// How long results of this query stay valid, after which they are re-executed on their next
+    /// access
+    ///
+    /// `None` means that they stay valid until one of their dependencies changes. The
+    /// time-to-live of queries that have one can be overriden at runtime with
+    /// [`Database::set_ttl`].
+    const TTL: Option<Duration> = None;
+}
+
+/// A query that can be called from its definition
+///
+/// Implementations are created with [`#[yeter::query]`][query] and [`#[yeter::input]`][input],
+/// along with the query definition.
+pub trait Query: QueryDef {
+    /// Calls the query, like the function it was created from
+    fn run(db: &Database, input: Self::Input) -> Rc<Self::Output>;
+}
+
+/// A type that gives access to a [`Database`]
+///
+/// Queries can take a reference to any type implementing this trait as their first argument,
+/// instead of a [`&Database`][Database]. This lets them access some typed context that is not
+/// tracked by the database, such as configuration, along with the database.
+pub trait AsDatabase {
+    /// Returns the database
+    fn as_database(&self) -> &Database;
+}
+
+impl AsDatabase for Database {
+    fn as_database(&self) -> &Database {
+        self
+    }
 }
 
 /// A query definition for an _input query_
 ///
-/// Implementations can be created with [`#[yeter::query]`][query] on a function with no body.
+/// Implementations can be created with [`#[yeter::query]`][query] on a function with no body, or
+/// with [`#[yeter::input]`][input].
 ///
 /// An _input query_ can be assigned a value using [`Database::set`].
-pub trait InputQueryDef: QueryDef<Output = Option<Self::OptionalOutput>> {
-    type OptionalOutput;
+pub trait InputQueryDef: QueryDef {
+    /// The output for inputs that were never set, and that have no [provider][Database::provide]
+    fn unset(input: Self::Input) -> Self::Output;
 }
 
 type RcAny = Rc<dyn Any + 'static>;
 
+/// The query that is used to track dependencies on external resources identified by `T`
+///
+/// Its input is the hash of the key of the resource.
+struct External<T>(PhantomData<T>);
+
+impl<T> QueryDef for External<T> {
+    type Input = u64;
+    type Output = ();
+}
+
+/// The input query that is set when a [provider][Database::provide] of the input query `Q` is
+/// registered
+///
+/// The values loaded by the provider depend on it, so that they stay in the database that owns
+/// the provider, and are loaded again when it is replaced.
+struct Provided<Q>(PhantomData<Q>);
+
+impl<Q> QueryDef for Provided<Q> {
+    type Input = ();
+    type Output = ();
+}
+
+/// The input query that holds the field number `FIELD` of the input struct `S`, of type `T`
+///
+/// It is used by the code generated by [`#[yeter::input]`][input] on structs.
+#[doc(hidden)]
+pub struct InputField<S, T, const FIELD: usize>(PhantomData<(S, T)>);
+
+impl<S, T, const FIELD: usize> QueryDef for InputField<S, T, FIELD> {
+    type Input = (S,);
+    type Output = T;
+}
+
+impl<S, T, const FIELD: usize> InputQueryDef for InputField<S, T, FIELD> {
+    fn unset(_: (S,)) -> T {
+        panic!("input structs can only be read from the database they were created in")
+    }
+}
+
+/// Re-executes a query call, to refresh its cache item
+type Refresh = Rc<dyn Fn(&Database)>;
+
+/// Compares the new output of a query with its previous one, for early cutoff
+type SameOutput<T> = fn(&T, &T) -> bool;
+
+/// Tells the current time, to know when time-to-lives are over
+type Clock = Rc<dyn Fn() -> Instant>;
+
+/// Loads the value of an input query that was not set
+type Provider<Q> = Rc<dyn Fn(<Q as QueryDef>::Input) -> <Q as QueryDef>::Output>;
+
+/// Identifies a query call: the query type and the hash of its input
+type QueryKey = (NsTypeId, u64);
+
+/// The cache of a single query, associating an input hash with the corresponding output
+///
+/// Items are behind an [`Rc`] so that they can be shared between forked databases.
+type QueryCache = HashMap<u64, Rc<CachedComputation>>;
+
+/// A cache item of a base database verified by an overlay, with the revision it was verified at
+/// and the time until which it is valid
+type BaseVerification = (Weak<CachedComputation>, usize, Option<Instant>);
+
+/// All the query caches of a database
+///
+/// Query caches are behind an [`Rc`] too, so that forking a database doesn't copy them.
+type Caches = HashMap<NsTypeId, Rc<QueryCache>>;
+
+/// Returns a mutable reference to a cache item
+///
+/// If the item (or the query cache it is part of) is shared with another database, it is cloned
+/// first, so that the other database doesn't see the modification.
+fn cache_item_mut(
+    caches: &mut Caches,
+    (q, input_hash): QueryKey,
+) -> Option<&mut CachedComputation> {
+    let cache = Rc::make_mut(caches.get_mut(&q)?);
+    Some(Rc::make_mut(cache.get_mut(&input_hash)?))
+}
+
+/// Hashes the input of a query call
+fn input_hash(input: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes a part of the input of a query, as selected with `#[yeter::key(...)]`
+#[doc(hidden)]
+pub fn key_hash(value: &impl Hash) -> u64 {
+    input_hash(value)
+}
+
+/// Checks whether a query call has a cache item
+fn cache_contains(caches: &Caches, (q, input_hash): &QueryKey) -> bool {
+    caches
+        .get(q)
+        .is_some_and(|cache| cache.contains_key(input_hash))
+}
+
 /// The main type to interact with Yéter
 ///
 /// This structure holds _caches_ and _effects_ for each query type.
@@ -44,24 +201,93 @@ pub struct Database {
     ///
     /// It associates a query name with its cache.
     /// A query cache associates an input hash with the corresponding output.
-    caches: RwLock<HashMap<NsTypeId, HashMap<u64, CachedComputation>>>,
-    /// Current call stack, to track dependencies
-    stack: ThreadLocal<RefCell<Vec<(NsTypeId, u64)>>>,
-    /// Effects that have been executed by the current query
-    effects: ThreadLocal<RefCell<AnyMap>>,
+    caches: RwLock<Caches>,
+    /// The database this one is an overlay of, if any
+    ///
+    /// Cache items that are not in `caches` are looked up there.
+    base: Option<Rc<Database>>,
+    /// Verifications of cache items of the base database done by this overlay
+    ///
+    /// They can't be recorded in the items, which are shared with the base and its other overlays.
+    /// Each verified item is kept with the revision it was verified at, and the time until which
+    /// it is valid.
+    verified_base_items: RefCell<HashMap<QueryKey, BaseVerification>>,
+    /// The current revision
+    ///
+    /// It goes up every time an input is set. It is shared with overlays and base databases, so
+    /// that revisions of cache items coming from any of them can be compared. Forks of a database
+    /// that is not an overlay start from its revision, and then have their own.
+    revision: Rc<Cell<usize>>,
+    /// Time-to-live of query results, set at runtime
+    ttls: RwLock<HashMap<NsTypeId, Option<Duration>>>,
+    /// The clock used instead of [`Instant::now`], if one was [set][Database::set_clock]
+    clock: RwLock<Option<Clock>>,
+    /// Functions that load the value of input queries that were not set
+    ///
+    /// They are [`Provider`]s, stored as [`Any`] because their type depends on the query.
+    providers: RwLock<HashMap<NsTypeId, RcAny>>,
+    /// Tables of [interned][Database::intern] values, shared with forks, overlays and base
+    /// databases
+    interner: Rc<Interner>,
+    /// Current call stack, to track dependencies and effects
+    stack: ThreadLocal<RefCell<Vec<Frame>>>,
+    /// Side effects produced outside of a query, that are attached to the next computed query
+    pending_effects: RefCell<AnyMap>,
+    /// [Tracked entities][Database::new_tracked] created by the queries that are being computed,
+    /// with the query call that created them
+    created: RefCell<Vec<(QueryKey, QueryKey, CachedComputation)>>,
+    /// Outputs that were [borrowed][Database::borrow_output], by address
+    borrowed: RefCell<HashMap<*const (), RcAny>>,
+    /// [Async queries][Database::run_async] that are being computed, to share their output with
+    /// the other calls awaiting it
+    in_flight: RefCell<HashMap<QueryKey, Rc<InFlight>>>,
+    /// The revision in which the computations were [cancelled][Database::cancel], if any
+    cancelled_at: Cell<Option<usize>>,
+    /// The number of cancellations requested with a [`CancelHandle`] since the database last
+    /// checked, shared with the handles
+    cancel_requests: Arc<AtomicUsize>,
+    /// The number of [`Database::catch`] calls that are running, in which computations can be
+    /// interrupted when they are cancelled
+    catching: Cell<usize>,
+}
+
+/// A query that is being computed
+struct Frame {
+    /// The query call, or `None` if dependencies are not tracked
+    key: Option<QueryKey>,
+    /// The other query calls it depended on so far
+    dependencies: Vec<QueryKey>,
+    /// The side effects it produced so far
+    effects: AnyMap,
+    /// Whether it read some state that is not tracked by the database
+    untracked: bool,
 }
 
 /// A cache item
-#[derive(Debug)]
+#[derive(Clone)]
 struct CachedComputation {
-    /// The version of this item (starts at 1 and goes up with every recomputation)
-    version: usize,
+    /// The revision at which the output last changed
+    changed_at: usize,
+    /// The last revision at which this item was known to be up to date
+    verified_at: usize,
     /// The other query calls this computation depends on
-    dependencies: Vec<(NsTypeId, u64)>,
+    dependencies: Vec<QueryKey>,
     /// The output
     value: RcAny,
     /// Saved side effects
     effects: AnyMap,
+    /// Whether the computation read some state that is not tracked by the database. If true,
+    /// this cache item is invalid as soon as a new revision starts.
+    untracked: bool,
+    /// When the time-to-live of this item ends, if it has one. Once it is over, this cache item
+    /// is invalid and should be recomputed.
+    expires_at: Option<Instant>,
+    /// When the time-to-live of this item or of one of its (transitive) dependencies ends
+    valid_until: Option<Instant>,
+    /// How to re-execute the query call, when verifying the items that depend on it
+    refresh: Option<Refresh>,
+    /// The [tracked entities][Database::new_tracked] created by the computation
+    created: Vec<QueryKey>,
     /// Wheter or not the associated query was redefined. If true, this cache item
     /// is invalid and should be recomputed.
     redefined: bool,
@@ -71,35 +297,33 @@ struct CachedComputation {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CycleError;
 
-struct UninitCachedComputationValue;
-
 impl CachedComputation {
-    fn new(version: usize) -> Self {
+    fn new(revision: usize, value: RcAny) -> Self {
         CachedComputation {
-            version,
-            dependencies: vec![],
-            value: Rc::new(UninitCachedComputationValue),
+            changed_at: revision,
+            verified_at: revision,
+            dependencies: Vec::new(),
+            value,
             redefined: false,
             effects: AnyMap::new(),
+            untracked: false,
+            expires_at: None,
+            valid_until: None,
+            refresh: None,
+            created: Vec::new(),
         }
     }
+}
 
-    fn new_init(version: usize, value: Rc<dyn Any>) -> Self {
-        CachedComputation {
-            version,
+impl Frame {
+    fn new(key: Option<QueryKey>) -> Self {
+        Frame {
+            key,
             dependencies: Vec::new(),
-            value,
-            redefined: false,
             effects: AnyMap::new(),
+            untracked: false,
         }
     }
-
-    fn is_uninit(&self) -> bool {
-        matches!(
-            self.value.downcast_ref::<UninitCachedComputationValue>(),
-            Some(UninitCachedComputationValue)
-        )
-    }
 }
 
 impl Database {
@@ -113,7 +337,7 @@ impl Database {
     /// Panics if a query ends up in a cyclic computation
     pub fn run<'input, F, Q>(&self, f: F, i: Q::Input) -> Rc<Q::Output>
     where
-        F: Fn(&Database, Q::Input) -> Q::Output,
+        F: FnOnce(&Database, Q::Input) -> Q::Output,
         Q: QueryDef,
         Q::Input: Hash + 'input,
         Q::Output: 'static,
@@ -124,105 +348,469 @@ impl Database {
     /// Tries to runs a query (or not if it the result is already in the cache)
     pub fn try_run<'input, F, Q>(&self, f: F, i: Q::Input) -> Result<Rc<Q::Output>, CycleError>
     where
-        F: Fn(&Database, Q::Input) -> Q::Output,
+        F: FnOnce(&Database, Q::Input) -> Q::Output,
         Q: QueryDef,
         Q::Input: Hash + 'input,
         Q::Output: 'static,
     {
-        let q = NsTypeId::of::<Q>();
+        self.execute::<F, Q>(f, i, |_, _| None, None)
+    }
+
+    /// Runs a query that can be called from its key
+    ///
+    /// If it creates [tracked entities][Database::new_tracked], its input is kept in the database
+    /// so that it can be re-executed when they are read while it is outdated.
+    ///
+    /// Panics if a query ends up in a cyclic computation
+    pub fn run_query<F, Q>(&self, f: F, i: Q::Input) -> Rc<Q::Output>
+    where
+        F: FnOnce(&Database, Q::Input) -> Q::Output,
+        Q: Query,
+        Q::Input: Hash + Clone + 'static,
+        Q::Output: 'static,
+    {
+        self.try_run_query::<F, Q>(f, i).unwrap()
+    }
+
+    /// Tries to run a [query called from its key][Database::run_query]
+    pub fn try_run_query<F, Q>(&self, f: F, i: Q::Input) -> Result<Rc<Q::Output>, CycleError>
+    where
+        F: FnOnce(&Database, Q::Input) -> Q::Output,
+        Q: Query,
+        Q::Input: Hash + Clone + 'static,
+        Q::Output: 'static,
+    {
+        let make_refresh = |_: &F, i: &Q::Input| {
+            let i = i.clone();
+            let refresh: Refresh = Rc::new(move |db| {
+                Q::run(db, i.clone());
+            });
+            Some(refresh)
+        };
+        self.execute::<F, Q>(f, i, make_refresh, None)
+    }
+
+    /// Keeps a query output alive as long as this database, and borrows it
+    ///
+    /// This is what queries declared with `#[yeter::query(borrow)]` return. The reference stays
+    /// valid when the output is invalidated: it then still refers to the output as it was
+    /// computed. Borrowed outputs are only released by [`Database::release_outputs`].
+    pub fn borrow_output<T: 'static>(&self, output: Rc<T>) -> &T {
+        let ptr = Rc::as_ptr(&output);
+        let mut borrowed = self.borrowed.borrow_mut();
+        borrowed.entry(ptr as *const ()).or_insert(output);
+        // SAFETY: the output is kept alive by `borrowed` until `release_outputs` is called, which
+        // requires the database to be borrowed mutably, so no reference given here is alive then
+        unsafe { &*ptr }
+    }
+
+    /// Releases the outputs that were [borrowed][Database::borrow_output]
+    ///
+    /// Outputs that are still in a query cache are not freed.
+    pub fn release_outputs(&mut self) {
+        self.borrowed.get_mut().clear();
+    }
+
+    /// Runs a _transparent query_, which is neither cached nor tracked as a dependency
+    ///
+    /// The query is executed on every call, and the queries it calls are recorded as dependencies
+    /// of the calling query, as if it called them directly. Since it is not on the query stack,
+    /// cyclic transparent queries are not detected.
+    pub fn run_transparent<F, Q>(&self, f: F, i: Q::Input) -> Rc<Q::Output>
+    where
+        F: FnOnce(&Database, Q::Input) -> Q::Output,
+        Q: QueryDef,
+    {
+        Rc::new(f(self, i))
+    }
+
+    /// Runs a query that can be re-executed while verifying the queries that depend on it
+    ///
+    /// This requires its input to be kept in the database, but allows _early cutoff_: if the
+    /// query has to be re-executed (because one of its dependencies changed, or because its
+    /// [time-to-live][QueryDef::TTL] is over) and its new output is equal to the previous one,
+    /// the queries that depend on it are not re-executed.
+    ///
+    /// Panics if a query ends up in a cyclic computation
+    pub fn run_refreshable<F, Q>(&self, f: F, i: Q::Input) -> Rc<Q::Output>
+    where
+        F: Fn(&Database, Q::Input) -> Q::Output + Clone + 'static,
+        Q: QueryDef,
+        Q::Input: Hash + Clone + 'static,
+        Q::Output: PartialEq + 'static,
+    {
+        self.try_run_refreshable::<F, Q>(f, i).unwrap()
+    }
+
+    /// Tries to run a [refreshable query][Database::run_refreshable]
+    pub fn try_run_refreshable<F, Q>(&self, f: F, i: Q::Input) -> Result<Rc<Q::Output>, CycleError>
+    where
+        F: Fn(&Database, Q::Input) -> Q::Output + Clone + 'static,
+        Q: QueryDef,
+        Q::Input: Hash + Clone + 'static,
+        Q::Output: PartialEq + 'static,
+    {
+        let make_refresh = |f: &F, i: &Q::Input| {
+            let (f, i) = (f.clone(), i.clone());
+            let refresh: Refresh = Rc::new(move |db| {
+                let _ = db.execute::<F, Q>(f.clone(), i.clone(), |_, _| None, Some(PartialEq::eq));
+            });
+            Some(refresh)
+        };
+        self.execute::<F, Q>(f, i, make_refresh, Some(PartialEq::eq))
+    }
 
-        let mut hasher = DefaultHasher::new();
-        i.hash(&mut hasher);
-        let input_hash = hasher.finish();
+    /// Runs a query
+    ///
+    /// `make_refresh` is called before re-executing it, to save a way to refresh its cache item,
+    /// and `same_output` is used to compare its new output with the previous one. Queries without
+    /// early cutoff only keep their way to be refreshed if they created tracked entities.
+    fn execute<F, Q>(
+        &self,
+        f: F,
+        i: Q::Input,
+        make_refresh: impl FnOnce(&F, &Q::Input) -> Option<Refresh>,
+        same_output: Option<SameOutput<Q::Output>>,
+    ) -> Result<Rc<Q::Output>, CycleError>
+    where
+        F: FnOnce(&Database, Q::Input) -> Q::Output,
+        Q: QueryDef,
+        Q::Input: Hash,
+        Q::Output: 'static,
+    {
+        let key = (NsTypeId::of::<Q>(), input_hash(&i));
 
         {
             let mut stack = self.stack.get_or_default().borrow_mut();
-            let stack_top = stack.iter().last().cloned();
-            stack.push((q, input_hash));
-
-            if let Some(stack_top) = stack_top {
-                let mut caches = self.caches.write().unwrap();
-                let cache = caches.get_mut(&stack_top.0).unwrap();
-                let cc = cache.get_mut(&stack_top.1).unwrap();
-                cc.dependencies.push((q, input_hash));
+            if stack.iter().any(|frame| frame.key == Some(key)) {
+                return Err(CycleError);
             }
-        };
 
-        let old_version = {
-            let caches = self.caches.read().unwrap();
-            let cache = caches.get(&q);
-            if let Some(c) = cache.and_then(|c| c.get(&input_hash)) {
-                if !c.redefined {
-                    let newest_dep = c
-                        .dependencies
-                        .iter()
-                        .map(|(f, k)| {
-                            caches
-                                .get(f)
-                                .expect("Uknown query (dependency of another query)")
-                                .get(k)
-                                .expect("A cached computation has a non-cached dependency")
-                                .version
-                        })
-                        .max()
-                        .unwrap_or(1);
-                    if c.version >= newest_dep {
-                        if let Ok(v) = c.value.clone().downcast() {
-                            return Ok(v);
-                        } else if c.is_uninit() {
-                            return Err(CycleError);
-                        } else {
-                            unimplemented!("impossible downcast")
-                        }
-                    } else {
-                        newest_dep
-                    }
-                } else {
-                    c.version
-                }
-            } else {
-                0
+            if let Some(frame) = stack.last_mut() {
+                frame.dependencies.push(key);
             }
+        }
+
+        if let Some((_, cc)) = self.verify(key, true) {
+            return Ok(cc
+                .value
+                .clone()
+                .downcast()
+                .expect("Cached computation was not of the correct type"));
+        }
+
+        self.unwind_if_cancelled();
+        let refresh = make_refresh(&f, &i);
+        let revision = self.revision.get();
+
+        let frame = self.new_frame(key);
+        self.stack.get_or_default().borrow_mut().push(frame);
+
+        let out = f(self, i);
+
+        let frame = self.stack.get_or_default().borrow_mut().pop().unwrap();
+        Ok(self.save::<Q>(key, revision, frame, out, refresh, same_output))
+    }
+
+    /// Saves the output of a query call that was just executed, with the dependencies and effects
+    /// recorded in its frame
+    ///
+    /// `revision` is the revision the query started executing in. If inputs were set during its
+    /// execution, the output is only valid for that revision, so it is re-executed on its next
+    /// access.
+    fn save<Q>(
+        &self,
+        key: QueryKey,
+        revision: usize,
+        frame: Frame,
+        out: Q::Output,
+        refresh: Option<Refresh>,
+        same_output: Option<SameOutput<Q::Output>>,
+    ) -> Rc<Q::Output>
+    where
+        Q: QueryDef,
+        Q::Output: 'static,
+    {
+        // Early cutoff: if the output didn't change, keep the previous one
+        let previous = self.find_item(key).map(|(_, cc)| cc).filter(|cc| {
+            let previous = cc.value.downcast_ref::<Q::Output>();
+            let same = previous.zip(same_output);
+            !cc.redefined && same.is_some_and(|(previous, same)| same(previous, &out))
+        });
+        let mut cc = match previous {
+            Some(previous) => CachedComputation {
+                verified_at: revision,
+                ..CachedComputation::clone(&previous)
+            },
+            None => CachedComputation::new(revision, Rc::new(out)),
         };
 
-        {
-            let mut caches = self.caches.write().unwrap();
-            let cc = CachedComputation::new(old_version + 1);
-            let cache = caches.entry(q).or_default();
-            cache.insert(input_hash, cc);
+        cc.expires_at = self.ttl::<Q>().map(|ttl| self.now() + ttl);
+        cc.valid_until = frame
+            .dependencies
+            .iter()
+            .filter_map(|&dep| self.find_item(dep)?.1.valid_until)
+            .chain(cc.expires_at)
+            .min();
+        cc.dependencies = frame.dependencies;
+        cc.effects = frame.effects;
+        cc.untracked = frame.untracked;
+        let created = self.take_created(key);
+        cc.created = created.iter().map(|(entity_key, _)| *entity_key).collect();
+        cc.refresh = refresh.filter(|_| same_output.is_some() || !created.is_empty());
+
+        let out = cc.value.clone();
+        let refresh = cc.refresh.clone();
+        self.store(key, cc);
+
+        // Tracked entities are stored after the query that created them, so that they end up in
+        // the same database when this one is an overlay
+        for (entity_key, entity) in created {
+            let refresh = refresh.clone();
+            self.store(entity_key, CachedComputation { refresh, ..entity });
+        }
+
+        out.downcast()
+            .expect("Cached computation was not of the correct type")
+    }
+
+    /// Creates the frame of a query call that is about to be computed
+    ///
+    /// It takes the side effects that were produced outside of a query.
+    fn new_frame(&self, key: QueryKey) -> Frame {
+        Frame {
+            effects: std::mem::take(&mut *self.pending_effects.borrow_mut()),
+            ..Frame::new(Some(key))
+        }
+    }
+
+    /// Runs a function without recording the queries it calls as dependencies of the current query
+    fn untracked<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.stack
+            .get_or_default()
+            .borrow_mut()
+            .push(Frame::new(None));
+        let res = f();
+        self.stack.get_or_default().borrow_mut().pop();
+        res
+    }
+
+    /// The time-to-live of the results of a query
+    fn ttl<Q: QueryDef>(&self) -> Option<Duration> {
+        let ttl = self.ttls.read().unwrap().get(&NsTypeId::of::<Q>()).copied();
+        match (ttl, &self.base) {
+            (Some(ttl), _) => ttl,
+            (None, Some(base)) => base.ttl::<Q>(),
+            (None, None) => Q::TTL,
+        }
+    }
+
+    /// Sets the time-to-live of the results of a query, overriding [`QueryDef::TTL`]
+    ///
+    /// `None` means that results stay valid until one of their dependencies changes. Results
+    /// that are already cached keep the time-to-live they were computed with.
+    ///
+    /// Panics if the query was not declared with a time-to-live. Such queries are
+    /// [refreshable][Database::run_refreshable], so that the queries depending on an expired
+    /// result benefit from early cutoff.
+    pub fn set_ttl<Q: QueryDef>(&self, ttl: Option<Duration>) {
+        assert!(
+            Q::TTL.is_some(),
+            "only the time-to-live of queries declared with one can be set"
+        );
+        let mut ttls = self.ttls.write().unwrap();
+        ttls.insert(NsTypeId::of::<Q>(), ttl);
+    }
+
+    /// Sets the clock that tells when time-to-lives are over, instead of [`Instant::now`]
+    ///
+    /// This lets tests make time pass without waiting. Forks keep the clock of the database they
+    /// were forked from, and overlays use the one of their base unless they have their own.
+    pub fn set_clock(&self, clock: impl Fn() -> Instant + 'static) {
+        *self.clock.write().unwrap() = Some(Rc::new(clock));
+    }
+
+    /// The current time, according to the clock of this database
+    fn now(&self) -> Instant {
+        let clock = self.clock.read().unwrap().clone();
+        match (clock, &self.base) {
+            (Some(clock), _) => clock(),
+            (None, Some(base)) => base.now(),
+            (None, None) => Instant::now(),
+        }
+    }
+
+    /// Returns the cached output of a query, if it is up to date
+    ///
+    /// Unlike [`Database::run`], this never executes anything, and when called from a query, it
+    /// doesn't record a dependency on the peeked query.
+    pub fn peek<'input, Q>(&self, i: Q::Input) -> Option<Rc<Q::Output>>
+    where
+        Q: QueryDef,
+        Q::Input: Hash + 'input,
+        Q::Output: 'static,
+    {
+        let key = (NsTypeId::of::<Q>(), input_hash(&i));
+        let (_, cc) = self.verify(key, false)?;
+        cc.value.clone().downcast().ok()
+    }
+
+    /// Finds the cache item of a query call, in this database or in its base
+    ///
+    /// Also returns the number of overlays that had to be traversed to find it (0 if it is
+    /// in this database).
+    fn find_item(&self, key: QueryKey) -> Option<(usize, Rc<CachedComputation>)> {
+        let cc = {
+            let caches = self.caches.read().unwrap();
+            caches.get(&key.0).and_then(|c| c.get(&key.1)).cloned()
         };
+        match cc {
+            Some(cc) => Some((0, cc)),
+            None => {
+                let (depth, cc) = self.base.as_ref()?.find_item(key)?;
+                Some((depth + 1, cc))
+            }
+        }
+    }
 
-        let out = Rc::new(f(self, i));
+    /// Finds the cache item of a query call, if it is up to date
+    ///
+    /// An item is up to date if none of its (transitive) dependencies changed since it was last
+    /// verified. Items coming from a base database are also rejected if one of their dependencies
+    /// is shadowed by this database.
+    ///
+    /// If `refresh` is true, outdated dependencies that
+    /// [can be refreshed][Database::run_refreshable] are re-executed, to check if their output
+    /// actually changed.
+    fn verify(&self, key: QueryKey, refresh: bool) -> Option<(usize, Rc<CachedComputation>)> {
+        let (depth, cc) = self.find_item(key)?;
+        if cc.redefined {
+            return None;
+        }
 
+        if cc
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= self.now())
         {
-            let mut stack = self.stack.get_or_default().borrow_mut();
-            stack.pop();
+            return None;
         }
 
-        {
-            let mut effects = self.effects.get_or_default().borrow_mut();
-            let effects = std::mem::take(&mut *effects);
+        // Items of the base database are verified again by each overlay, since their dependencies
+        // may be shadowed
+        let revision = self.revision.get();
+        let verified = match depth {
+            0 => Some((cc.verified_at, cc.valid_until)),
+            _ => self.base_verification(key, &cc),
+        };
+        if let Some((verified_at, valid_until)) = verified {
+            let expiring = valid_until.is_some_and(|valid_until| valid_until <= self.now());
+            if verified_at == revision && !expiring {
+                return Some((depth, cc));
+            }
+        }
+        if cc.untracked && cc.verified_at < revision {
+            return None;
+        }
 
+        let mut valid_until = cc.expires_at;
+        for &dep in &cc.dependencies {
+            let dep_cc = match self.verify(dep, refresh) {
+                None if refresh => self.refresh(dep),
+                dep_cc => dep_cc,
+            };
+            let (dep_depth, dep_cc) = dep_cc?;
+            if dep_depth < depth || dep_cc.changed_at > cc.verified_at {
+                return None;
+            }
+            valid_until = valid_until.into_iter().chain(dep_cc.valid_until).min();
+        }
+
+        if depth == 0 {
             let mut caches = self.caches.write().unwrap();
-            let cache = caches.get_mut(&q).unwrap();
-            let cc = cache.get_mut(&input_hash).unwrap();
-            cc.effects = effects;
-            cc.value = out;
-            cc.value
-                .clone()
-                .downcast()
-                .map(Ok)
-                .expect("Cached computation was not of the correct type")
+            if let Some(cc) = cache_item_mut(&mut caches, key) {
+                cc.verified_at = revision;
+                cc.valid_until = valid_until;
+            }
+        } else {
+            let verification = (Rc::downgrade(&cc), revision, valid_until);
+            self.verified_base_items
+                .borrow_mut()
+                .insert(key, verification);
+        }
+
+        Some((depth, cc))
+    }
+
+    /// Returns the revision at which this overlay last verified an item of its base database,
+    /// and the time until which it is valid, unless the item was replaced since then
+    fn base_verification(
+        &self,
+        key: QueryKey,
+        cc: &Rc<CachedComputation>,
+    ) -> Option<(usize, Option<Instant>)> {
+        let verified = self.verified_base_items.borrow();
+        let (item, verified_at, valid_until) = verified.get(&key)?;
+        (item.as_ptr() == Rc::as_ptr(cc)).then_some((*verified_at, *valid_until))
+    }
+
+    /// Re-executes a query call whose cache item is outdated, if it can be refreshed
+    ///
+    /// Returns the new cache item.
+    fn refresh(&self, key: QueryKey) -> Option<(usize, Rc<CachedComputation>)> {
+        let (_, cc) = self.find_item(key)?;
+        let refresh = cc.refresh.as_ref()?;
+        self.untracked(|| refresh(self));
+        self.verify(key, true)
+    }
+
+    /// Saves a cache item
+    ///
+    /// If this database is an overlay and none of the dependencies of the item are shadowed by
+    /// this database, the item is saved in the base database so that it can be reused by
+    /// other overlays.
+    ///
+    /// The tracked entities created by the item it replaces, and not by this one, are removed.
+    fn store(&self, key: QueryKey, cc: CachedComputation) {
+        let mut caches = self.caches.write().unwrap();
+
+        if let Some(base) = &self.base {
+            let is_shadowed = |dep| cache_contains(&caches, dep);
+            if !cc.dependencies.iter().any(is_shadowed) {
+                if cache_contains(&caches, &key) {
+                    Rc::make_mut(caches.get_mut(&key.0).unwrap()).remove(&key.1);
+                }
+                drop(caches);
+                return base.store(key, cc);
+            }
+        }
+
+        let created = cc.created.clone();
+        let cache = Rc::make_mut(caches.entry(key.0).or_default());
+        let replaced = cache.insert(key.1, Rc::new(cc));
+        let removed = replaced.iter().flat_map(|replaced| &replaced.created);
+        for entity in removed.filter(|entity| !created.contains(entity)) {
+            if let Some(cache) = caches.get_mut(&entity.0) {
+                Rc::make_mut(cache).remove(&entity.1);
+            }
         }
     }
 
+    /// Lists all the cache items that are visible from this database
+    fn visible_items(&self) -> Vec<(QueryKey, Rc<CachedComputation>)> {
+        let caches = self.caches.read().unwrap();
+        let own = caches
+            .iter()
+            .flat_map(|(&q, cache)| cache.iter().map(move |(&h, cc)| ((q, h), cc.clone())));
+        let from_base = self.base.iter().flat_map(|base| base.visible_items());
+        let from_base = from_base.filter(|(key, _)| !cache_contains(&caches, key));
+        own.chain(from_base).collect()
+    }
+
     /// Returns a side effect collection
     pub fn effect<T: 'static + Clone + Send>(&self) -> Vec<T> {
-        let caches = self.caches.read().unwrap();
-        caches
-            .values()
-            .flat_map(|x| x.values())
-            .filter_map(|cc| {
+        self.visible_items()
+            .into_iter()
+            .filter_map(|(_, cc)| {
                 let cell = cc.effects.get::<Vec<T>>()?;
                 Some(cell.clone())
             })
@@ -231,10 +819,46 @@ impl Database {
     }
 
     /// Produces a side effect
+    ///
+    /// It is attached to the query that is currently being computed. Effects produced outside of
+    /// a query are attached to the next query that is computed.
     pub fn do_effect<T: 'static + Clone + Send>(&self, eff: T) {
-        let mut effects = self.effects.get_or_default().borrow_mut();
-        let vec = effects.entry::<Vec<T>>().or_default();
-        vec.push(eff);
+        let mut stack = self.stack.get_or_default().borrow_mut();
+        match stack.last_mut() {
+            Some(frame) => frame.effects.entry::<Vec<T>>().or_default().push(eff),
+            None => {
+                let mut effects = self.pending_effects.borrow_mut();
+                effects.entry::<Vec<T>>().or_default().push(eff);
+            }
+        }
+    }
+
+    /// Reports that the current query read some state that is not tracked by the database
+    ///
+    /// This should be called by queries that read the clock, environment variables, the file
+    /// system or anything else that can change without the database knowing. Such queries are
+    /// re-executed once per revision, that is the first time they are called after an input was
+    /// [set][Database::set] or after [`Database::new_revision`]. Queries annotated with
+    /// `#[yeter::query(volatile)]` call this function automatically.
+    ///
+    /// Calling it outside of a query does nothing.
+    pub fn report_untracked_read(&self) {
+        let mut stack = self.stack.get_or_default().borrow_mut();
+        if let Some(frame) = stack.last_mut() {
+            frame.untracked = true;
+        }
+    }
+
+    /// Starts a new revision
+    ///
+    /// This is done automatically when an input is [set][Database::set], but can also be used to
+    /// force queries that [read untracked state][Database::report_untracked_read] to be
+    /// re-executed.
+    pub fn new_revision(&self) -> usize {
+        let revision = self.revision.get() + 1;
+        self.revision.set(revision);
+        self.cancel_requests.store(0, Ordering::Relaxed);
+        revision
     }
 
     /// Defines the a value
@@ -242,23 +866,170 @@ impl Database {
     where
         Q: InputQueryDef,
         Q::Input: Hash + 'input,
-        Q::OptionalOutput: 'static,
+        Q::Output: 'static,
     {
-        let q = NsTypeId::of::<Q>();
+        self.set_item((NsTypeId::of::<Q>(), input_hash(&input)), Rc::new(output));
+    }
+
+    /// Saves the value of an input in a new revision
+    fn set_item(&self, (q, input_hash): QueryKey, value: RcAny) {
+        let revision = self.new_revision();
+        let cc = CachedComputation::new(revision, value);
+
+        let mut caches = self.caches.write().unwrap();
+        let cache = Rc::make_mut(caches.entry(q).or_default());
+        cache.insert(input_hash, Rc::new(cc));
+    }
 
-        let mut hasher = DefaultHasher::new();
-        input.hash(&mut hasher);
-        let input_hash = hasher.finish();
+    /// Records that the current query depends on an external resource
+    ///
+    /// The resource is identified by a token type `T` and a key. It is up to the code that
+    /// modifies the resource to let the database know about it by calling
+    /// [`Database::bump_external`], which invalidates the queries that read it.
+    pub fn external<T: 'static>(&self, key: impl Hash) {
+        let key_hash = input_hash(&key);
+        let _ = self.execute::<_, External<T>>(|_, _| (), key_hash, |_, _| None, None);
+    }
+
+    /// Signals that an external resource changed
+    ///
+    /// All the queries that called [`Database::external`] with the same token type and key will be
+    /// re-executed on their next access.
+    pub fn bump_external<T: 'static>(&self, key: impl Hash) {
+        let key_hash = input_hash(&key);
+        let q = NsTypeId::of::<External<T>>();
+        self.set_item((q, input_hash(&key_hash)), Rc::new(()));
+    }
 
-        let output: Rc<dyn Any> = Rc::new(output);
+    /// Registers a function that loads the value of an input query the first time it is needed
+    ///
+    /// When an input query is called with an input that was never [set][Database::set], `provider`
+    /// is called to load its value, instead of using [`InputQueryDef::unset`]. The loaded value is cached like
+    /// any other input, and can later be overwritten with [`Database::set`].
+    ///
+    /// Values that were loaded by a previous provider are loaded again, but values that were cached
+    /// as [`None`] because there was no provider are not affected, so providers should be
+    /// registered before running queries. The values loaded by the provider of an overlay are only
+    /// visible from the overlay.
+    pub fn provide<Q>(&self, provider: impl Fn(Q::Input) -> Q::Output + 'static)
+    where
+        Q: InputQueryDef,
+        Q::Input: 'static,
+        Q::Output: 'static,
+    {
+        let provider: Provider<Q> = Rc::new(provider);
+        let mut providers = self.providers.write().unwrap();
+        providers.insert(NsTypeId::of::<Q>(), Rc::new(provider));
+        drop(providers);
 
-        let default_cc = CachedComputation::new_init(1, output.clone());
+        let q = NsTypeId::of::<Provided<Q>>();
+        self.set_item((q, input_hash(&())), Rc::new(()));
+    }
 
-        let mut caches = self.caches.write().unwrap();
-        let cache = caches.entry(q).or_default();
-        let cc = cache.entry(input_hash).or_insert(default_cc);
-        cc.value = output;
-        cc.version += 1;
+    /// Loads the value of an input query that was not set
+    ///
+    /// The [provider][Database::provide] of the query is used if there is one, otherwise
+    /// [`InputQueryDef::unset`] is. This is what input queries created with
+    /// [`#[yeter::query]`][query] or [`#[yeter::input]`][input] do when they are called with an
+    /// input that was not set.
+    pub fn load_input<Q>(&self, input: Q::Input) -> Q::Output
+    where
+        Q: InputQueryDef,
+        Q::Input: 'static,
+        Q::Output: 'static,
+    {
+        match self.provider::<Q>() {
+            Some(provider) => {
+                let _ = self.execute::<_, Provided<Q>>(|_, _| (), (), |_, _| None, None);
+                provider(input)
+            }
+            None => Q::unset(input),
+        }
+    }
+
+    /// Finds the provider of an input query, in this database or in its base
+    fn provider<Q>(&self) -> Option<Provider<Q>>
+    where
+        Q: InputQueryDef,
+        Q::Input: 'static,
+        Q::Output: 'static,
+    {
+        let provider = self
+            .providers
+            .read()
+            .unwrap()
+            .get(&NsTypeId::of::<Q>())
+            .cloned();
+        match provider {
+            Some(provider) => provider.downcast_ref::<Provider<Q>>().cloned(),
+            None => self.base.as_ref()?.provider::<Q>(),
+        }
+    }
+
+    /// Creates a copy of this database that can be modified independently
+    ///
+    /// Cached computations are shared with the original database until one of the two databases
+    /// recomputes or redefines them, so forking is cheap even for large databases. This makes it
+    /// possible to run queries on a hypothetical state (for instance after [setting][Database::set]
+    /// a few inputs) without affecting the original database.
+    ///
+    /// Side effects that are produced by the current query are not carried over to the fork.
+    /// Revisions started by one of the two databases don't affect the other one, unless they are
+    /// overlays of the same base.
+    pub fn fork(&self) -> Database {
+        // Overlays compare the revisions of their items with the ones of their base
+        let revision = match &self.base {
+            Some(_) => self.revision.clone(),
+            None => Rc::new(Cell::new(self.revision.get())),
+        };
+        Database {
+            caches: RwLock::new(self.caches.read().unwrap().clone()),
+            base: self.base.clone(),
+            verified_base_items: RefCell::new(self.verified_base_items.borrow().clone()),
+            revision,
+            ttls: RwLock::new(self.ttls.read().unwrap().clone()),
+            clock: RwLock::new(self.clock.read().unwrap().clone()),
+            providers: RwLock::new(self.providers.read().unwrap().clone()),
+            interner: self.interner.clone(),
+            stack: Default::default(),
+            pending_effects: Default::default(),
+            created: Default::default(),
+            borrowed: Default::default(),
+            in_flight: Default::default(),
+            cancelled_at: Default::default(),
+            cancel_requests: Default::default(),
+            catching: Default::default(),
+        }
+    }
+
+    /// Creates an overlay of a database
+    ///
+    /// An overlay reads inputs and cached results from its base database, but inputs that are
+    /// [set][Database::set] on the overlay shadow the ones of the base. Results that depend on
+    /// shadowed inputs are only cached in the overlay, while the other ones are saved in the base
+    /// database, to be reused by the base and all of its overlays.
+    ///
+    /// Inputs that are later set on the base database are visible from the overlay, unless they
+    /// are shadowed.
+    pub fn overlay(base: &Rc<Database>) -> Database {
+        Database {
+            caches: Default::default(),
+            base: Some(base.clone()),
+            verified_base_items: Default::default(),
+            revision: base.revision.clone(),
+            ttls: Default::default(),
+            clock: Default::default(),
+            providers: Default::default(),
+            interner: base.interner.clone(),
+            stack: Default::default(),
+            pending_effects: Default::default(),
+            created: Default::default(),
+            borrowed: Default::default(),
+            in_flight: Default::default(),
+            cancelled_at: Default::default(),
+            cancel_requests: Default::default(),
+            catching: Default::default(),
+        }
     }
 }
 
@@ -277,10 +1048,68 @@ impl Database {
 ///
 /// # Syntax
 ///
-/// `#[yeter::query]` doesn't expect any attribute parameters. It must be applied to a function with
-/// or without a body, whose first argument is present and is typed as a
-/// [`&yeter::Database`][Database]. The function cannot be an instance method (i.e. have a `self`
-/// receiver as its first argument).
+/// `#[yeter::query]` must be applied to a function with or without a body, whose first argument is
+/// present and is typed as a [`&yeter::Database`][Database], or as a reference to another type
+/// implementing [`AsDatabase`].
+///
+/// Queries with a body can be `async fn`s. They are then run with [`Database::run_async`], which
+/// shares the output of concurrent calls with the same arguments, and must be awaited. They
+/// don't implement [`Query`], and can't be `incremental`, `transparent` or have a time-to-live.
+///
+/// It can also be applied to a method that takes `self` by value and then the database, such as
+/// `fn exports(self, db: &yeter::Database) -> Vec<Export>`, in an `impl ModuleId` block annotated
+/// with [`#[yeter::queries]`][queries]. `self` is then the first part of the key of the query, and
+/// must implement [`Hash`].
+///
+/// Queries with a body that take another type than [`Database`] are called with the value they
+/// were given, so they don't implement [`Query`], and can't have a time-to-live.
+///
+/// Queries that implement [`Query`] and have no generic parameters or borrowed arguments are run
+/// with [`Database::run_query`], so their arguments must be [`Clone`]. They can then be
+/// re-executed when the [tracked entities][Database::new_tracked] they created are read while
+/// they are outdated.
+///
+/// The following options can be given as attribute parameters:
+///
+/// - `volatile`: the query reads some state that is not tracked by the database (see
+///   [`Database::report_untracked_read`]), and is re-executed once per revision.
+/// - `ttl = "30s"`: results are re-executed on their next access once they are older than the given
+///   duration (see [`QueryDef::TTL`]). Durations are written with the `ms`, `s`, `m`, `h` and `d`
+///   units, and can be combined as in `"1h30m"`. Such queries
+///   [can be refreshed][Database::run_refreshable], so their arguments must be [`Clone`] and
+///   `'static`, and their output must be [`PartialEq`].
+/// - `incremental`: the query takes its previous output as its second argument, typed as an
+///   `Option<`[`yeter::Previous<T>`][Previous]`>` where `T` is its return type, and can patch it
+///   instead of starting from scratch (see [`Database::run_incremental`]). This argument is not
+///   part of the query input.
+/// - `transparent`: the query is not memoized and is executed on every call (see
+///   [`Database::run_transparent`]). The queries it calls are dependencies of the calling query.
+/// - `clone`: the query function returns a clone of the output instead of an
+///   [`Rc<T>`][std::rc::Rc], which is convenient for outputs that are cheap to clone.
+/// - `borrow`: the query function returns a reference to the output, that lives as long as the
+///   borrow of the database (see [`Database::borrow_output`]).
+/// - `cache_err = false`: the query returns a [`Result`], and its `Err` outputs are only cached
+///   until the next revision, so that transient failures are retried. `Ok` outputs are cached as
+///   usual.
+/// - `interface(Trait)`: the query has no body, and calls the method with the same name of the
+///   `dyn Trait` [installed][Database::install] in the database, with the database and its
+///   arguments. Its results are invalidated when another implementation is installed.
+///
+/// Along with the query function, the macro declares a `try_<name>` function (or method) taking
+/// the same arguments, which returns a `Result<T, `[`yeter::Error`][Error]`>` where `T` is the
+/// return type of the query function. It detects cycles, [cancellation][Database::cancel] and
+/// panics (see [`Database::catch`]) instead of panicking, so that queries returning a `Result`
+/// can propagate these errors with `?`. Async queries don't have a `try_` variant.
+///
+/// By default, all the arguments after the database are part of the cache key of the query, and
+/// must implement [`Hash`]. Arguments of a query with a body can be given these attributes:
+///
+/// - `#[yeter::no_key]`: the argument is passed to the query, but is not part of its key. This
+///   is useful for handles, callbacks or settings that don't change the output.
+/// - `#[yeter::key(expr)]`: the hash of `expr` is part of the key, instead of the argument.
+///   The expression can refer to the arguments of the query by their names.
+///
+/// These queries don't implement [`Query`], as they can't be called from their key only.
 ///
 /// # Example
 ///
@@ -315,3 +1144,125 @@ impl Database {
 /// # }
 /// ```
 pub use yeter_macros::query;
+
+/// Declares an _input query_ that doesn't return an [Option], or an _input struct_
+///
+/// # Usage
+///
+/// This attribute is applied to functions without a body, like input queries declared with
+/// [`#[yeter::query]`][query]. Their value for a given input is set with [`Database::set`], or
+/// loaded by a [provider][Database::provide], but unlike [`#[yeter::query]`][query] input queries,
+/// they return an [`Rc<T>`][std::rc::Rc] directly instead of an optional value.
+///
+/// Calling such a query with an input that was never set panics, with a message naming the query
+/// and its arguments (which must then implement [`Debug`]). Alternatively, a default value can be
+/// given with `#[yeter::input(default = expr)]`.
+///
+/// # Example
+///
+/// ```
+/// # use std::path::PathBuf;
+/// #[yeter::input]
+/// fn source(db: &yeter::Database, path: PathBuf) -> String;
+///
+/// #[yeter::input(default = 4)]
+/// fn tab_width(db: &yeter::Database) -> usize;
+///
+/// # fn main() {
+/// let db = yeter::Database::new();
+/// db.set::<source>(("main.rs".into(),), "fn main() {}".into());
+/// assert_eq!(*source(&db, "main.rs".into()), "fn main() {}");
+/// assert_eq!(*tab_width(&db), 4);
+/// # }
+/// ```
+///
+/// # Input structs
+///
+/// When applied to a struct with named fields, the struct is turned into a cheap copyable id, and
+/// each of its fields is stored in the database as a separate input. The id derives `Clone`,
+/// `Copy`, `Debug`, `Eq`, `Hash`, `Ord`, `PartialEq` and `PartialOrd`, so deriving these traits on
+/// the struct has no effect. The struct gets:
+///
+/// - a `new(db, fields...)` constructor, which creates a new input with the given field values;
+/// - a getter for each field, such as `file.text(db)`, which returns an [`Rc<T>`][std::rc::Rc];
+/// - a setter for each field, such as `file.set_text(db, value)`.
+///
+/// A query that calls a getter only depends on that field, so it is not invalidated when the
+/// other fields of the input change. Getters and setters have the visibility of their field.
+///
+/// ```
+/// # use std::path::PathBuf;
+/// #[yeter::input]
+/// struct SourceFile {
+///     text: String,
+///     path: PathBuf,
+/// }
+///
+/// # fn main() {
+/// let db = yeter::Database::new();
+/// let file = SourceFile::new(&db, "fn main() {}".into(), "main.rs".into());
+/// file.set_text(&db, "fn main() { loop {} }".into());
+/// assert_eq!(*file.path(&db), PathBuf::from("main.rs"));
+/// # }
+/// ```
+pub use yeter_macros::input;
+
+/// Declares a group of queries, as a trait or as a module, or the query methods of an impl block
+///
+/// # Modules
+///
+/// When applied to an inline module, every function of the module becomes a query, as if it was
+/// annotated with [`#[yeter::query]`][query]. Functions can still be annotated to give options,
+/// or with [`#[yeter::input]`][input]. The module gets a `Group` type, which implements
+/// [`QueryGroup`].
+///
+/// # Traits
+///
+/// When applied to a trait, every method becomes a query, whose database is `&self`. Methods with
+/// a body are computed queries, and methods without one are input queries, which get a
+/// `set_<name>` setter method. Methods can be annotated with [`#[yeter::query]`][query] or
+/// [`#[yeter::input]`][input] to give options, and their return type is adjusted accordingly.
+///
+/// The trait is implemented for every type implementing [`AsDatabase`] and its supertraits, and
+/// `dyn Trait` implements [`QueryGroup`]. The bodies of the methods of a trait with supertraits are
+/// called with the value the query was called with, as a `&dyn Trait`, so they can use the
+/// supertraits to access some context, such as configuration. Like other queries that don't take a
+/// [`&Database`][Database], they can't have a time-to-live.
+///
+/// # Impl blocks
+///
+/// When applied to an inherent impl block, the methods annotated with
+/// [`#[yeter::query]`][query] become queries. Since impl blocks can't contain types, the query
+/// type of a method is declared next to the impl block, as `__yeter_<Type>_<method>`, and the
+/// original body is kept in a hidden `__yeter_<method>` method. Impl blocks are not groups.
+///
+/// # Groups
+///
+/// The queries of a group can be enumerated at runtime with [`QueryGroup::queries`], and their
+/// caches can be inspected and dropped all at once, with [`Database::group_stats`],
+/// [`Database::invalidate_group`] and [`Database::clear_group`]. Queries of a group can't be
+/// generic.
+///
+/// # Example
+///
+/// ```
+/// #[yeter::queries]
+/// trait Typeck {
+///     fn source(&self, name: String) -> Option<String>;
+///
+///     fn len(&self, name: String) -> usize {
+///         self.source(name).as_ref().as_ref().map_or(0, String::len)
+///     }
+/// }
+///
+/// # fn main() {
+/// let db = yeter::Database::new();
+/// db.set_source("main.rs".into(), Some("fn main() {}".into()));
+/// assert_eq!(*db.len("main.rs".into()), 12);
+///
+/// let names = <dyn Typeck as yeter::QueryGroup>::queries();
+/// let names = names.iter().map(|q| q.name()).collect::<Vec<_>>();
+/// assert_eq!(names, ["source", "len"]);
+/// # }
+/// ```
+pub use yeter_macros::queries;
@@ -6,6 +6,6 @@ pub struct NsTypeId(usize);
 
 impl NsTypeId {
     pub fn of<T>() -> Self {
-        Self(Self::of::<T> as usize)
+        Self(Self::of::<T> as *const () as usize)
     }
 }
@@ -0,0 +1,34 @@
+use crate::{Database, Query, QueryDef};
+use std::{hash::Hash, marker::PhantomData, rc::Rc};
+
+/// The query that holds the part of the output of `Q` selected by `F`
+struct Projection<Q, F, T>(PhantomData<(Q, F, T)>);
+
+impl<Q: QueryDef, F, T> QueryDef for Projection<Q, F, T> {
+    type Input = Q::Input;
+    type Output = T;
+}
+
+impl Database {
+    /// Runs a query and selects a part of its output
+    ///
+    /// The selected part is cached as the output of a lightweight derived query, which has
+    /// [early cutoff][Database::run_refreshable]: the queries that call `select` only depend on
+    /// the selected part, and are not invalidated when other parts of the output change.
+    ///
+    /// Each `project` function defines its own derived query, so it should be written in a single
+    /// place (typically as a closure such as `|analysis| &analysis.exports`).
+    ///
+    /// Panics if a query ends up in a cyclic computation
+    pub fn select<Q, F, T>(&self, input: Q::Input, project: F) -> Rc<T>
+    where
+        Q: Query,
+        Q::Input: Hash + Clone + 'static,
+        Q::Output: 'static,
+        F: Fn(&Q::Output) -> &T + Clone + 'static,
+        T: Clone + PartialEq + 'static,
+    {
+        let f = move |db: &Database, input| project(&Q::run(db, input)).clone();
+        self.run_refreshable::<_, Projection<Q, F, T>>(f, input)
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{input_hash, CachedComputation, Database, NsTypeId, QueryDef, QueryKey};
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    rc::Rc,
+};
+
+/// An entity of type `T` created by a query with [`Database::new_tracked`]
+///
+/// It is cheap to copy, compare and hash, so it can be used as the input of other queries. Its
+/// fields are read with [`Database::tracked`].
+pub struct Tracked<T> {
+    /// Hash of the query call that created the entity and of its identity
+    hash: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// The query whose cache items hold the fields of tracked entities of type `T`
+struct TrackedFields<T>(PhantomData<T>);
+
+impl<T> QueryDef for TrackedFields<T> {
+    type Input = u64;
+    type Output = T;
+}
+
+impl<T> Tracked<T> {
+    fn key(self) -> QueryKey {
+        (NsTypeId::of::<TrackedFields<T>>(), self.hash)
+    }
+}
+
+impl Database {
+    /// Creates a tracked entity from the current query
+    ///
+    /// Entities are identified by the query call that creates them and by `identity`: when the
+    /// query is re-executed and creates an entity with the same identity, it gets the same id.
+    /// If its fields didn't change either, the queries that read them are not invalidated.
+    ///
+    /// Panics if called outside of a query, or twice with the same identity in the same query.
+    pub fn new_tracked<T>(&self, identity: impl Hash, fields: T) -> Tracked<T>
+    where
+        T: PartialEq + 'static,
+    {
+        let creator = self
+            .stack
+            .get_or_default()
+            .borrow()
+            .last()
+            .and_then(|frame| frame.key)
+            .expect("tracked entities can only be created by queries");
+
+        let id = Tracked {
+            hash: input_hash(&(creator, identity)),
+            _marker: PhantomData,
+        };
+        let key = id.key();
+
+        let mut created = self.created.borrow_mut();
+        assert!(
+            created.iter().all(|(_, k, _)| *k != key),
+            "tracked entity created twice with the same identity"
+        );
+
+        let revision = self.revision.get();
+        let previous = self.find_item(key).map(|(_, cc)| cc).filter(|cc| {
+            let previous = cc.value.downcast_ref::<T>();
+            previous.is_some_and(|previous| *previous == fields)
+        });
+        let mut cc = match previous {
+            Some(previous) => CachedComputation {
+                verified_at: revision,
+                ..CachedComputation::clone(&previous)
+            },
+            None => CachedComputation::new(revision, Rc::new(fields)),
+        };
+        cc.dependencies = vec![creator];
+
+        created.push((creator, key, cc));
+        id
+    }
+
+    /// Removes the tracked entities created by a query call from the pending ones
+    pub(crate) fn take_created(&self, creator: QueryKey) -> Vec<(QueryKey, CachedComputation)> {
+        let mut created = self.created.borrow_mut();
+        let (taken, pending) = created.drain(..).partition(|(c, _, _)| *c == creator);
+        *created = pending;
+        taken.into_iter().map(|(_, key, cc)| (key, cc)).collect()
+    }
+
+    /// Reads the fields of a tracked entity
+    ///
+    /// When called from a query, it only depends on this entity, and not on the query that
+    /// created it. If that query is outdated, it is re-executed first if it can be called from
+    /// its key (see [`Database::run_query`] and [`Database::run_refreshable`]). Otherwise this
+    /// panics, since its fields may be stale: such queries must be called again before their
+    /// entities are read.
+    ///
+    /// Also panics if the entity was not created in this database (or one sharing its cache
+    /// items), or if the last execution of its query didn't create it again.
+    pub fn tracked<T>(&self, id: Tracked<T>) -> Rc<T>
+    where
+        T: 'static,
+    {
+        let key = id.key();
+        if let Some(frame) = self.stack.get_or_default().borrow_mut().last_mut() {
+            frame.dependencies.push(key);
+        }
+
+        let cc = match self.verify(key, true).or_else(|| self.refresh(key)) {
+            Some((_, cc)) => cc,
+            None => {
+                let (_, cc) = self
+                    .find_item(key)
+                    .expect("unknown tracked entity, or its query didn't create it again");
+                let creator = cc.dependencies[0];
+                if self.verify(creator, false).is_some() {
+                    panic!("tracked entity read after its query didn't create it again");
+                }
+                panic!(
+                    "the query that created this tracked entity is outdated, and can't be \
+                    refreshed: call it again before reading its entities"
+                );
+            }
+        };
+        cc.value
+            .clone()
+            .downcast()
+            .expect("Cached computation was not of the correct type")
+    }
+}
+
+impl<T> Clone for Tracked<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Tracked<T> {}
+
+impl<T> PartialEq for Tracked<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl<T> Eq for Tracked<T> {}
+
+impl<T> PartialOrd for Tracked<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Tracked<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.hash.cmp(&other.hash)
+    }
+}
+
+impl<T> Hash for Tracked<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state)
+    }
+}
+
+impl<T> fmt::Debug for Tracked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tracked({:x})", self.hash)
+    }
+}
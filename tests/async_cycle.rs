@@ -0,0 +1,58 @@
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::{pin, Pin},
+    task::{Context, Poll, Waker},
+};
+use yeter::Database;
+
+/// Yields to the executor once
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Polls two futures concurrently until both are done, on the current thread
+fn block_on_both<A: Future, B: Future>(a: A, b: B) {
+    let (mut a, mut b) = (pin!(a), pin!(b));
+    let (mut a_done, mut b_done) = (false, false);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        a_done = a_done || a.as_mut().poll(&mut cx).is_ready();
+        b_done = b_done || b.as_mut().poll(&mut cx).is_ready();
+        if a_done && b_done {
+            return;
+        }
+    }
+}
+
+#[yeter::query]
+async fn ping(db: &Database) -> u32 {
+    YieldOnce(false).await;
+    *pong(db).await + 1
+}
+
+#[yeter::query]
+async fn pong(db: &Database) -> u32 {
+    YieldOnce(false).await;
+    *ping(db).await + 1
+}
+
+#[test]
+fn cycle_through_in_flight_computations() {
+    let db = Database::new();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| block_on_both(ping(&db), pong(&db))));
+    let payload = result.expect_err("the cycle was not detected");
+    let message = payload.downcast_ref::<&str>().copied().unwrap_or_default();
+    assert_eq!(message, "cyclic computation of an async query");
+}
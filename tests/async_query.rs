@@ -0,0 +1,129 @@
+use std::{
+    cell::Cell,
+    future::{poll_fn, Future},
+    pin::{pin, Pin},
+    task::{Context, Poll, Waker},
+};
+use yeter::Database;
+
+thread_local! {
+    static EXECUTIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Yields to the executor once, like a call to a subprocess would
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Polls two futures concurrently until both are done
+async fn join<A: Future, B: Future>(a: A, b: B) -> (A::Output, B::Output) {
+    let (mut a, mut b) = (pin!(a), pin!(b));
+    let (mut out_a, mut out_b) = (None, None);
+    poll_fn(|cx| {
+        if out_a.is_none() {
+            if let Poll::Ready(out) = a.as_mut().poll(cx) {
+                out_a = Some(out);
+            }
+        }
+        if out_b.is_none() {
+            if let Poll::Ready(out) = b.as_mut().poll(cx) {
+                out_b = Some(out);
+            }
+        }
+        match (&out_a, &out_b) {
+            (Some(_), Some(_)) => Poll::Ready((out_a.take().unwrap(), out_b.take().unwrap())),
+            _ => Poll::Pending,
+        }
+    })
+    .await
+}
+
+/// Runs a future on the current thread
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(out) = future.as_mut().poll(&mut cx) {
+            return out;
+        }
+    }
+}
+
+#[yeter::query]
+fn source(db: &Database, name: String) -> Option<String>;
+
+#[yeter::query]
+async fn formatted(db: &Database, name: String) -> String {
+    EXECUTIONS.with(|e| e.set(e.get() + 1));
+    YieldOnce(false).await;
+    let source = source(db, name);
+    source.as_deref().unwrap_or_default().trim().to_string()
+}
+
+#[yeter::query]
+async fn both(db: &Database, a: String, b: String) -> String {
+    let (a, b) = join(formatted(db, a), formatted(db, b)).await;
+    format!("{}\n{}", a, b)
+}
+
+#[test]
+fn shared_computation() {
+    let db = Database::new();
+    db.set::<source>(("a".into(),), Some(" fn a() {} ".into()));
+
+    let calls = join(formatted(&db, "a".into()), formatted(&db, "a".into()));
+    let (first, second) = block_on(calls);
+    assert_eq!(*first, "fn a() {}");
+    assert_eq!(*second, "fn a() {}");
+    assert_eq!(EXECUTIONS.with(Cell::get), 1);
+}
+
+#[test]
+fn dependencies_across_await() {
+    let db = Database::new();
+    db.set::<source>(("a".into(),), Some("a ".into()));
+    db.set::<source>(("b".into(),), Some("b ".into()));
+    let out = block_on(both(&db, "a".into(), "b".into()));
+    assert_eq!(*out, "a\nb");
+
+    db.set::<source>(("b".into(),), Some("c ".into()));
+    assert_eq!(
+        db.peek::<formatted>(("a".into(),)).as_deref(),
+        Some(&"a".into())
+    );
+    assert_eq!(db.peek::<formatted>(("b".into(),)), None);
+    let out = block_on(both(&db, "a".into(), "b".into()));
+    assert_eq!(*out, "a\nc");
+}
+
+#[yeter::query(clone)]
+async fn length(db: &Database, name: String) -> usize {
+    let len = source(db, name).as_deref().map_or(0, str::len);
+    YieldOnce(false).await;
+    len
+}
+
+#[test]
+fn input_set_while_pending() {
+    let db = Database::new();
+    db.set::<source>(("a".into(),), Some("a".into()));
+
+    let mut pending = pin!(length(&db, "a".into()));
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(pending.as_mut().poll(&mut cx).is_pending());
+    db.set::<source>(("a".into(),), Some("abc".into()));
+    assert_eq!(block_on(pending), 1);
+
+    assert_eq!(block_on(length(&db, "a".into())), 3);
+}
@@ -0,0 +1,37 @@
+use std::cell::Cell;
+use yeter::Database;
+
+thread_local! {
+    static ATTEMPTS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::input(default = 0)]
+fn unrelated(db: &Database) -> u32;
+
+#[yeter::query(cache_err = false)]
+fn run_tool(_db: &Database, succeed_after: usize) -> Result<usize, String> {
+    let attempts = ATTEMPTS.with(|calls| {
+        calls.set(calls.get() + 1);
+        calls.get()
+    });
+    if attempts > succeed_after {
+        Ok(attempts)
+    } else {
+        Err("tool crashed".into())
+    }
+}
+
+#[test]
+fn errors_retried_next_revision() {
+    let db = Database::new();
+    assert!(run_tool(&db, 1).is_err());
+    assert!(run_tool(&db, 1).is_err());
+    assert_eq!(ATTEMPTS.with(Cell::get), 1);
+
+    db.set::<unrelated>((), 1);
+    assert_eq!(*run_tool(&db, 1), Ok(2));
+
+    db.set::<unrelated>((), 2);
+    assert_eq!(*run_tool(&db, 1), Ok(2));
+    assert_eq!(ATTEMPTS.with(Cell::get), 2);
+}
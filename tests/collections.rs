@@ -0,0 +1,80 @@
+use std::cell::Cell;
+use yeter::{Database, IncrementalMap, IncrementalVec};
+
+thread_local! {
+    static DOUBLE_CALLS: Cell<usize> = const { Cell::new(0) };
+    static COUNT_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::query]
+fn double(db: &Database, map: IncrementalMap<String, usize>, key: String) -> Option<usize> {
+    DOUBLE_CALLS.with(|calls| calls.set(calls.get() + 1));
+    map.get(db, key).map(|value| *value * 2)
+}
+
+#[yeter::query]
+fn count(db: &Database, map: IncrementalMap<String, usize>) -> usize {
+    COUNT_CALLS.with(|calls| calls.set(calls.get() + 1));
+    map.len(db)
+}
+
+#[yeter::query]
+fn sum(db: &Database, list: IncrementalVec<usize>) -> usize {
+    (0..list.len(db)).map(|i| *list.get(db, i).unwrap()).sum()
+}
+
+#[test]
+fn map_entries() {
+    let db = Database::new();
+    let map = IncrementalMap::new(&db);
+    map.insert(&db, "a".to_owned(), 1);
+    assert_eq!(*double(&db, map, "a".into()), Some(2));
+    assert_eq!(*count(&db, map), 1);
+
+    map.insert(&db, "b".to_owned(), 2);
+    assert_eq!(*double(&db, map, "a".into()), Some(2));
+    assert_eq!(*count(&db, map), 2);
+    assert_eq!(DOUBLE_CALLS.with(Cell::get), 1);
+
+    map.insert(&db, "b".to_owned(), 3);
+    assert_eq!(*count(&db, map), 2);
+    assert_eq!(COUNT_CALLS.with(Cell::get), 2);
+
+    map.remove(&db, "a".to_owned());
+    assert_eq!(*double(&db, map, "a".into()), None);
+    assert_eq!(*map.keys(&db), ["b"]);
+}
+
+#[test]
+fn vec_elements() {
+    let db = Database::new();
+    let list = IncrementalVec::new(&db);
+    list.push(&db, 1);
+    list.push(&db, 2);
+    list.push(&db, 3);
+    assert_eq!(*sum(&db, list), 6);
+
+    list.set(&db, 1, 5);
+    assert_eq!(*sum(&db, list), 9);
+    list.pop(&db);
+    assert_eq!(*sum(&db, list), 6);
+    assert_eq!(list.get(&db, 2), None);
+}
+
+#[test]
+fn map_key_order() {
+    let db = Database::new();
+    let map = IncrementalMap::new(&db);
+    for i in 0..20_000 {
+        map.insert(&db, i.to_string(), i);
+    }
+    assert_eq!(*count(&db, map), 20_000);
+
+    map.remove(&db, "0".to_owned());
+    map.insert(&db, "0".to_owned(), 0);
+    map.insert(&db, "1".to_owned(), 10);
+    let keys = map.keys(&db);
+    assert_eq!(keys.first().map(String::as_str), Some("1"));
+    assert_eq!(keys.last().map(String::as_str), Some("0"));
+    assert_eq!(map.get(&db, "1".to_owned()).as_deref(), Some(&10));
+}
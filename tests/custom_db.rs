@@ -0,0 +1,77 @@
+use std::ops::Deref;
+use yeter::{AsDatabase, Database};
+
+struct Compiler {
+    db: Database,
+    optimize: bool,
+}
+
+impl AsDatabase for Compiler {
+    fn as_database(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Deref for Compiler {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+#[yeter::input(default = String::new())]
+fn source(db: &Compiler, name: String) -> String;
+
+#[yeter::query]
+fn compile(db: &Compiler, name: String) -> String {
+    let source = source(db, name);
+    if db.optimize {
+        source.trim().to_owned()
+    } else {
+        String::clone(&source)
+    }
+}
+
+trait Options {
+    fn optimize(&self) -> bool;
+}
+
+impl Options for Compiler {
+    fn optimize(&self) -> bool {
+        self.optimize
+    }
+}
+
+#[yeter::queries]
+trait Linker: Options {
+    #[yeter::query(clone)]
+    fn link(&self, name: String) -> String {
+        let level = if self.optimize() { "O2" } else { "O0" };
+        format!("{}.{}", name, level)
+    }
+}
+
+#[yeter::query(clone)]
+fn build(compiler: &Compiler, name: String) -> String {
+    compiler.link(name)
+}
+
+#[test]
+fn context() {
+    let compiler = Compiler {
+        db: Database::new(),
+        optimize: true,
+    };
+    compiler.set::<source>(("a".into(),), " main ".into());
+    assert_eq!(*compile(&compiler, "a".into()), "main");
+}
+
+#[test]
+fn group_context() {
+    let compiler = Compiler {
+        db: Database::new(),
+        optimize: false,
+    };
+    assert_eq!(build(&compiler, "main".into()), "main.O0");
+}
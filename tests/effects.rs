@@ -0,0 +1,28 @@
+use yeter::Database;
+
+#[yeter::query]
+fn name(db: &Database) -> Option<String>;
+
+#[yeter::query]
+fn greeting(db: &Database) -> String {
+    let name = name(db);
+    let name = Option::as_ref(&name).map_or("world", String::as_str);
+    db.do_effect(format!("greeted {}", name));
+    format!("hello {}", name)
+}
+
+#[test]
+fn effects() {
+    let db = Database::new();
+    db.do_effect("started".to_owned());
+    assert!(db.effect::<String>().is_empty());
+
+    greeting(&db);
+    let mut effects = db.effect::<String>();
+    effects.sort();
+    assert_eq!(effects, ["greeted world", "started"]);
+
+    db.set::<name>((), Some("yeter".into()));
+    greeting(&db);
+    assert_eq!(db.effect::<String>(), ["greeted yeter"]);
+}
@@ -0,0 +1,46 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use yeter::Database;
+
+/// Token type for the package registry index on disk
+enum RegistryIndex {}
+
+thread_local! {
+    static INDEX: RefCell<HashMap<&'static str, &'static str>> = RefCell::new(HashMap::new());
+    static READS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn publish(name: &'static str, version: &'static str) {
+    INDEX.with(|index| index.borrow_mut().insert(name, version));
+}
+
+#[yeter::query]
+fn latest_version(db: &Database, name: &'static str) -> Option<&'static str> {
+    db.external::<RegistryIndex>(name);
+    READS.with(|reads| reads.set(reads.get() + 1));
+    INDEX.with(|index| index.borrow().get(name).copied())
+}
+
+#[yeter::query]
+fn lockfile(db: &Database) -> String {
+    let serde = latest_version(db, "serde");
+    let syn = latest_version(db, "syn");
+    format!("serde={:?} syn={:?}", serde, syn)
+}
+
+#[test]
+fn bump() {
+    let db = Database::new();
+    publish("serde", "1.0.0");
+    publish("syn", "2.0.0");
+    assert_eq!(*lockfile(&db), r#"serde=Some("1.0.0") syn=Some("2.0.0")"#);
+    assert_eq!(READS.with(Cell::get), 2);
+
+    publish("serde", "1.0.1");
+    assert_eq!(*lockfile(&db), r#"serde=Some("1.0.0") syn=Some("2.0.0")"#);
+
+    db.bump_external::<RegistryIndex>("serde");
+    assert_eq!(*lockfile(&db), r#"serde=Some("1.0.1") syn=Some("2.0.0")"#);
+    // Only the query that read "serde" was re-executed
+    assert_eq!(READS.with(Cell::get), 3);
+}
@@ -0,0 +1,61 @@
+use yeter::{Database, Error};
+
+#[yeter::query]
+fn depth(db: &Database, n: u32) -> Option<u32>;
+
+#[yeter::query(clone)]
+fn chain(db: &Database, n: u32) -> Result<u32, Error> {
+    match *depth(db, n) {
+        Some(next) => Ok(try_chain(db, next)?? + 1),
+        None => Ok(0),
+    }
+}
+
+#[yeter::query]
+fn checked(_db: &Database, n: u32) -> u32 {
+    assert!(n < 10, "{} is too large", n);
+    n
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct Node(u32);
+
+#[yeter::queries]
+impl Node {
+    #[yeter::query(clone)]
+    fn length(self, db: &Database) -> Result<u32, Error> {
+        chain(db, self.0)
+    }
+}
+
+#[test]
+fn cycle_and_panic() {
+    let db = Database::new();
+    db.set::<depth>((0,), Some(1));
+    db.set::<depth>((1,), Some(2));
+    assert_eq!(chain(&db, 0), Ok(2));
+    assert_eq!(Node(1).try_length(&db), Ok(Ok(1)));
+
+    db.set::<depth>((2,), Some(0));
+    assert_eq!(chain(&db, 0), Err(Error::Cycle));
+
+    db.set::<depth>((2,), None);
+    assert_eq!(chain(&db, 0), Ok(2));
+
+    assert_eq!(try_checked(&db, 3).map(|n| *n), Ok(3));
+    assert_eq!(
+        try_checked(&db, 12),
+        Err(Error::Panicked("12 is too large".into()))
+    );
+}
+
+#[test]
+fn cancellation() {
+    let db = Database::new();
+    db.cancel();
+    assert_eq!(try_chain(&db, 0), Err(Error::Cancelled));
+    assert_eq!(chain(&db, 0), Ok(0));
+
+    db.set::<depth>((0,), Some(1));
+    assert_eq!(try_chain(&db, 0), Ok(Ok(1)));
+}
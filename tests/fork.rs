@@ -0,0 +1,48 @@
+use yeter::{Database, Error};
+
+#[yeter::query]
+fn list(db: &Database) -> Option<Vec<usize>>;
+
+#[yeter::query]
+fn sum(db: &Database) -> usize {
+    let list = list(db);
+    list.as_ref().as_deref().unwrap_or_default().iter().sum()
+}
+
+#[test]
+fn independent_inputs() {
+    let db = Database::new();
+    db.set::<list>((), Some(vec![1, 2, 3]));
+    assert_eq!(*sum(&db), 6);
+
+    let fork = db.fork();
+    fork.set::<list>((), Some(vec![1]));
+    assert_eq!(*sum(&fork), 1);
+    assert_eq!(*sum(&db), 6);
+
+    db.set::<list>((), Some(vec![]));
+    assert_eq!(*sum(&db), 0);
+    assert_eq!(*sum(&fork), 1);
+}
+
+#[test]
+fn shares_cached_values() {
+    let db = Database::new();
+    db.set::<list>((), Some(vec![4, 5]));
+
+    let fork = db.fork();
+    assert_eq!(list(&fork), list(&db));
+    assert!(std::rc::Rc::ptr_eq(&list(&fork), &list(&db)));
+}
+
+#[test]
+fn own_revisions() {
+    let db = Database::new();
+    db.set::<list>((), Some(vec![1, 2]));
+    db.cancel();
+
+    let fork = db.fork();
+    fork.set::<list>((), Some(vec![3]));
+    assert_eq!(try_sum(&db), Err(Error::Cancelled));
+    assert_eq!(try_sum(&fork).as_deref(), Ok(&3));
+}
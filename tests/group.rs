@@ -0,0 +1,68 @@
+use std::cell::Cell;
+
+thread_local! {
+    static EXECUTIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::queries]
+trait Typeck {
+    fn source(&self, name: String) -> Option<String>;
+
+    #[yeter::query(clone)]
+    fn word_count(&self, name: String) -> usize {
+        EXECUTIONS.with(|e| e.set(e.get() + 1));
+        let source = self.source(name);
+        source.as_ref().as_ref().map_or(0, |s| s.split_whitespace().count())
+    }
+}
+
+#[yeter::queries]
+mod lexing {
+    use yeter::Database;
+
+    pub fn line(db: &Database, n: usize) -> Option<String>;
+
+    pub fn line_len(db: &Database, n: usize) -> usize {
+        line(db, n).as_ref().as_ref().map_or(0, String::len)
+    }
+}
+
+#[test]
+fn trait_group() {
+    let db = yeter::Database::new();
+    db.set_source("a".into(), Some("one two three".into()));
+    assert_eq!(db.word_count("a".into()), 3);
+    assert_eq!(db.word_count("b".into()), 0);
+
+    let queries = <dyn Typeck as yeter::QueryGroup>::queries();
+    let queries = queries
+        .iter()
+        .map(|q| (q.name(), q.is_input()))
+        .collect::<Vec<_>>();
+    assert_eq!(queries, [("source", true), ("word_count", false)]);
+
+    let stats = db.group_stats::<dyn Typeck>();
+    let entries = stats.iter().map(|s| (s.name, s.entries)).collect::<Vec<_>>();
+    assert_eq!(entries, [("source", 2), ("word_count", 2)]);
+
+    EXECUTIONS.with(|e| e.set(0));
+    db.invalidate_group::<dyn Typeck>();
+    assert_eq!(db.word_count("a".into()), 3);
+    assert_eq!(EXECUTIONS.with(Cell::get), 1);
+}
+
+#[test]
+fn module_group() {
+    let db = yeter::Database::new();
+    db.set::<lexing::line>((0,), Some("hello".into()));
+    assert_eq!(*lexing::line_len(&db, 0), 5);
+
+    let names = <lexing::Group as yeter::QueryGroup>::queries();
+    let names = names.iter().map(|q| q.name()).collect::<Vec<_>>();
+    assert_eq!(names, ["line", "line_len"]);
+
+    db.clear_group::<lexing::Group>();
+    let stats = db.group_stats::<lexing::Group>();
+    assert!(stats.iter().all(|s| s.entries == 0));
+    assert_eq!(*lexing::line_len(&db, 0), 0);
+}
@@ -0,0 +1,72 @@
+use std::{cell::Cell, collections::HashMap};
+use yeter::{Database, Previous};
+
+thread_local! {
+    static WORD_COUNTS: Cell<usize> = const { Cell::new(0) };
+    static CLONES: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::input(default = Vec::new())]
+fn file_names(db: &Database) -> Vec<String>;
+
+#[yeter::input(default = String::new())]
+fn file(db: &Database, name: String) -> String;
+
+#[derive(PartialEq)]
+struct Counts(HashMap<String, usize>);
+
+impl Clone for Counts {
+    fn clone(&self) -> Self {
+        CLONES.with(|clones| clones.set(clones.get() + 1));
+        Counts(self.0.clone())
+    }
+}
+
+#[yeter::query(incremental)]
+fn word_counts(db: &Database, previous: Option<Previous<Counts>>) -> Counts {
+    let names = file_names(db);
+    let previous = previous.filter(|previous| !previous.changed::<file_names>(&()));
+    let (mut counts, changed) = match previous {
+        Some(previous) => {
+            let changed = names
+                .iter()
+                .filter(|name| previous.changed::<file>(&((*name).clone(),)))
+                .cloned()
+                .collect();
+            (previous.into_output(), changed)
+        }
+        None => (Counts(HashMap::new()), Vec::clone(&names)),
+    };
+
+    for name in changed {
+        WORD_COUNTS.with(|calls| calls.set(calls.get() + 1));
+        let count = file(db, name.clone()).split_whitespace().count();
+        counts.0.insert(name, count);
+    }
+    counts
+}
+
+#[test]
+fn patch_previous_output() {
+    let db = Database::new();
+    db.set::<file>(("a".into(),), "one two".into());
+    db.set::<file>(("b".into(),), "three".into());
+    db.set::<file_names>((), vec!["a".into(), "b".into()]);
+    assert_eq!(word_counts(&db).0["a"], 2);
+    assert_eq!(WORD_COUNTS.with(Cell::get), 2);
+
+    db.set::<file>(("b".into(),), "three four five".into());
+    assert_eq!(word_counts(&db).0["b"], 3);
+    assert_eq!(word_counts(&db).0["a"], 2);
+    assert_eq!(WORD_COUNTS.with(Cell::get), 3);
+
+    // The query still depends on the file it didn't read again
+    db.set::<file>(("a".into(),), "one".into());
+    assert_eq!(word_counts(&db).0["a"], 1);
+    assert_eq!(WORD_COUNTS.with(Cell::get), 4);
+
+    db.set::<file_names>((), vec!["a".into()]);
+    assert!(!word_counts(&db).0.contains_key("b"));
+    assert_eq!(WORD_COUNTS.with(Cell::get), 5);
+    assert_eq!(CLONES.with(Cell::get), 0);
+}
@@ -0,0 +1,34 @@
+use std::path::PathBuf;
+use yeter::Database;
+
+#[yeter::input]
+fn source(db: &Database, path: PathBuf) -> String;
+
+#[yeter::input(default = Vec::new())]
+fn open_files(db: &Database) -> Vec<PathBuf>;
+
+#[yeter::query]
+fn total_len(db: &Database) -> usize {
+    open_files(db)
+        .iter()
+        .map(|path| source(db, path.clone()).len())
+        .sum()
+}
+
+#[test]
+fn default() {
+    let db = Database::new();
+    assert_eq!(*total_len(&db), 0);
+
+    db.set::<source>(("main.rs".into(),), "fn main() {}".into());
+    db.set::<open_files>((), vec!["main.rs".into()]);
+    assert_eq!(*total_len(&db), 12);
+}
+
+#[test]
+#[should_panic(expected = r#"input `source("lib.rs")` was never set"#)]
+fn never_set() {
+    let db = Database::new();
+    db.set::<open_files>((), vec!["lib.rs".into()]);
+    total_len(&db);
+}
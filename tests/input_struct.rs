@@ -0,0 +1,46 @@
+use std::{cell::Cell, path::PathBuf};
+use yeter::Database;
+
+thread_local! {
+    static EXTENSION_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A source file
+#[yeter::input]
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct SourceFile {
+    text: String,
+    path: PathBuf,
+}
+
+#[yeter::query]
+fn extension(db: &Database, file: SourceFile) -> Option<String> {
+    EXTENSION_CALLS.with(|calls| calls.set(calls.get() + 1));
+    let path = file.path(db);
+    Some(path.extension()?.to_str()?.to_owned())
+}
+
+#[yeter::query]
+fn line_count(db: &Database, file: SourceFile) -> usize {
+    file.text(db).lines().count()
+}
+
+#[test]
+fn per_field_dependencies() {
+    let db = Database::new();
+    let file = SourceFile::new(&db, "fn main() {}".into(), "main.rs".into());
+    let other = SourceFile::new(&db, String::new(), "main.rs".into());
+    assert_ne!(file, other);
+
+    assert_eq!(extension(&db, file).as_deref(), Some("rs"));
+    assert_eq!(*line_count(&db, file), 1);
+
+    file.set_text(&db, "fn main() {\n}".into());
+    assert_eq!(*line_count(&db, file), 2);
+    assert_eq!(extension(&db, file).as_deref(), Some("rs"));
+    assert_eq!(EXTENSION_CALLS.with(Cell::get), 1);
+
+    file.set_path(&db, "main.c".into());
+    assert_eq!(extension(&db, file).as_deref(), Some("c"));
+    assert_eq!(EXTENSION_CALLS.with(Cell::get), 2);
+}
@@ -0,0 +1,55 @@
+use yeter::Database;
+
+trait Backend {
+    fn codegen(&self, db: &Database, function: String) -> String;
+}
+
+struct Assembly;
+
+impl Backend for Assembly {
+    fn codegen(&self, db: &Database, function: String) -> String {
+        format!("{}: ret {}", function, optimization_level(db))
+    }
+}
+
+struct Bytecode;
+
+impl Backend for Bytecode {
+    fn codegen(&self, _db: &Database, function: String) -> String {
+        format!("{}: RETURN", function)
+    }
+}
+
+#[yeter::input(default = 0)]
+fn optimization_level(db: &Database) -> u8;
+
+#[yeter::query(interface(Backend))]
+fn codegen(db: &Database, function: String) -> String;
+
+#[yeter::query]
+fn program(db: &Database) -> Vec<String> {
+    vec![
+        codegen(db, "main".into()).to_string(),
+        codegen(db, "helper".into()).to_string(),
+    ]
+}
+
+#[test]
+fn install() {
+    let db = Database::new();
+    db.install::<dyn Backend>(Box::new(Assembly));
+    assert_eq!(*program(&db), ["main: ret 0", "helper: ret 0"]);
+
+    db.set::<optimization_level>((), 2);
+    assert_eq!(*codegen(&db, "main".into()), "main: ret 2");
+
+    db.install::<dyn Backend>(Box::new(Bytecode));
+    assert_eq!(*program(&db), ["main: RETURN", "helper: RETURN"]);
+}
+
+#[test]
+#[should_panic(expected = "no implementation of `dyn interface::Backend` was installed")]
+fn missing_implementation() {
+    let db = Database::new();
+    codegen(&db, "main".into());
+}
@@ -0,0 +1,36 @@
+use std::cell::Cell;
+use yeter::{Database, Id};
+
+thread_local! {
+    static LENGTH_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::query]
+fn name_length(db: &Database, name: Id<String>) -> usize {
+    LENGTH_CALLS.with(|calls| calls.set(calls.get() + 1));
+    db.lookup(name).len()
+}
+
+#[test]
+fn same_value_same_id() {
+    let db = Database::new();
+    let foo = db.intern("foo".to_owned());
+    let bar = db.intern("bar".to_owned());
+    assert_ne!(foo, bar);
+    assert_eq!(foo, db.intern("foo".to_owned()));
+    assert_eq!(*db.lookup(bar), "bar");
+
+    let fork = db.fork();
+    assert_eq!(fork.intern("bar".to_owned()), bar);
+    let baz = fork.intern("baz".to_owned());
+    assert_eq!(*db.lookup(baz), "baz");
+}
+
+#[test]
+fn query_input() {
+    let db = Database::new();
+    let hello = db.intern("hello".to_owned());
+    assert_eq!(*name_length(&db, hello), 5);
+    assert_eq!(*name_length(&db, db.intern("hello".to_owned())), 5);
+    assert_eq!(LENGTH_CALLS.with(Cell::get), 1);
+}
@@ -0,0 +1,92 @@
+use std::cell::RefCell;
+use std::thread;
+use yeter::{Database, Error, Previous};
+
+thread_local! {
+    static STEPS: RefCell<Vec<u32>> = const { RefCell::new(Vec::new()) };
+}
+
+#[yeter::query]
+fn step(db: &Database, i: u32) -> u32 {
+    STEPS.with(|steps| steps.borrow_mut().push(i));
+    if i == 2 {
+        // Another thread cancels the computations while this one is running
+        let handle = db.cancel_handle();
+        thread::spawn(move || handle.cancel()).join().unwrap();
+    }
+    i
+}
+
+#[yeter::query]
+fn steps(db: &Database, n: u32) -> u32 {
+    (0..n).map(|i| *step(db, i)).sum()
+}
+
+#[test]
+fn cancel_mid_run() {
+    let db = Database::new();
+    assert_eq!(try_steps(&db, 5), Err(Error::Cancelled));
+    assert_eq!(STEPS.with(|steps| steps.take()), [0, 1, 2]);
+    assert_eq!(db.peek::<steps>((5,)), None);
+
+    db.new_revision();
+    assert_eq!(try_steps(&db, 5).as_deref(), Ok(&10));
+    assert_eq!(STEPS.with(|steps| steps.take()), [3, 4]);
+}
+
+#[yeter::input(default = 10)]
+fn limit(db: &Database) -> u32;
+
+#[yeter::query]
+fn checked(db: &Database, n: u32) -> u32 {
+    assert!(n < *limit(db), "{} is too large", n);
+    n
+}
+
+#[yeter::query]
+fn total(db: &Database, n: u32) -> u32 {
+    (0..=n).map(|i| *checked(db, i)).sum()
+}
+
+#[test]
+fn panic_in_nested_query() {
+    let db = Database::new();
+    db.set::<limit>((), 3);
+    assert_eq!(
+        try_total(&db, 4),
+        Err(Error::Panicked("3 is too large".into()))
+    );
+    assert_eq!(db.peek::<total>((4,)), None);
+    assert_eq!(db.peek::<checked>((2,)).as_deref(), Some(&2));
+
+    // The interrupted queries are not left on the stack, so this is not a cycle
+    db.set::<limit>((), 5);
+    assert_eq!(try_total(&db, 4).as_deref(), Ok(&10));
+    assert_eq!(*total(&db, 4), 10);
+}
+
+#[yeter::input(default = 0)]
+fn version(db: &Database) -> u32;
+
+#[yeter::query(incremental)]
+fn history(db: &Database, previous: Option<Previous<Vec<u32>>>) -> Vec<u32> {
+    let version = *version(db);
+    assert_ne!(version, 2, "version 2 is broken");
+    let mut history = previous.map_or_else(Vec::new, |previous| Vec::clone(&previous.output));
+    history.push(version);
+    history
+}
+
+#[test]
+fn interrupted_incremental_query() {
+    let db = Database::new();
+    assert_eq!(*history(&db), [0]);
+    db.set::<version>((), 1);
+    assert_eq!(*history(&db), [0, 1]);
+
+    // The previous output was taken by the interrupted execution
+    db.set::<version>((), 2);
+    assert!(try_history(&db).is_err());
+    db.set::<version>((), 3);
+    assert_eq!(*history(&db), [3]);
+}
@@ -0,0 +1,36 @@
+use std::cell::RefCell;
+use yeter::Database;
+
+struct Logger(RefCell<Vec<String>>);
+
+#[yeter::query]
+fn compile(
+    _db: &Database,
+    file: String,
+    #[yeter::no_key] logger: &Logger,
+    #[yeter::key(target.to_lowercase())] target: String,
+) -> String {
+    logger.0.borrow_mut().push(format!("compiling {file}"));
+    format!("{file} for {target}")
+}
+
+#[test]
+fn excluded_arguments() {
+    let db = Database::new();
+    let logger = Logger(RefCell::new(Vec::new()));
+    let first = Logger(RefCell::new(Vec::new()));
+    assert_eq!(
+        *compile(&db, "a.rs".into(), &first, "x86".into()),
+        "a.rs for x86"
+    );
+    assert_eq!(
+        *compile(&db, "a.rs".into(), &logger, "X86".into()),
+        "a.rs for x86"
+    );
+    assert_eq!(
+        *compile(&db, "b.rs".into(), &logger, "X86".into()),
+        "b.rs for X86"
+    );
+    assert_eq!(*first.0.borrow(), ["compiling a.rs"]);
+    assert_eq!(*logger.0.borrow(), ["compiling b.rs"]);
+}
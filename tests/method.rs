@@ -0,0 +1,51 @@
+use std::cell::Cell;
+use yeter::Database;
+
+thread_local! {
+    static EXECUTIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::query]
+fn module_source(db: &Database, module: ModuleId) -> Option<String>;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct ModuleId(u32);
+
+#[yeter::queries]
+impl ModuleId {
+    #[yeter::query]
+    fn exports(self, db: &Database) -> Vec<String> {
+        EXECUTIONS.with(|e| e.set(e.get() + 1));
+        let source = module_source(db, self);
+        let source = source.as_deref().unwrap_or_default();
+        source.split_whitespace().map(String::from).collect()
+    }
+
+    #[yeter::query(clone)]
+    fn parent(self, db: &Database, levels: u32) -> Option<Self> {
+        let _ = db;
+        self.0.checked_sub(levels).map(ModuleId)
+    }
+}
+
+#[test]
+fn method() {
+    let db = Database::new();
+    let module = ModuleId(1);
+    db.set::<module_source>((module,), Some("f g".into()));
+
+    assert_eq!(*module.exports(&db), ["f", "g"]);
+    assert_eq!(*module.exports(&db), ["f", "g"]);
+    assert_eq!(*ModuleId(2).exports(&db), Vec::<String>::new());
+    assert_eq!(EXECUTIONS.with(Cell::get), 2);
+    let cached = db.peek::<__yeter_ModuleId_exports>((module,));
+    assert_eq!(cached.as_deref().map(Vec::len), Some(2));
+
+    db.set::<module_source>((module,), Some("h".into()));
+    assert_eq!(*module.exports(&db), ["h"]);
+    assert_eq!(EXECUTIONS.with(Cell::get), 3);
+
+    assert_eq!(module.parent(&db, 1), Some(ModuleId(0)));
+    assert_eq!(module.parent(&db, 2), None);
+    assert_eq!(module.try_parent(&db, 1), Ok(Some(ModuleId(0))));
+}
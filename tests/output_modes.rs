@@ -0,0 +1,56 @@
+use yeter::Database;
+
+#[yeter::input(default = String::new())]
+fn text(db: &Database) -> String;
+
+#[yeter::query(clone)]
+fn length(db: &Database) -> usize {
+    text(db).len()
+}
+
+#[yeter::query(borrow)]
+fn words(db: &Database) -> Vec<String> {
+    text(db).split_whitespace().map(str::to_owned).collect()
+}
+
+#[yeter::query(borrow)]
+fn first_word<'a>(db: &Database, default: &'a str) -> String {
+    words(db).first().map_or(default, String::as_str).to_owned()
+}
+
+#[derive(Clone, Copy, Hash)]
+struct Word(usize);
+
+#[yeter::queries]
+impl Word {
+    #[yeter::query(borrow)]
+    fn text(self, db: &Database) -> String {
+        words(db).get(self.0).cloned().unwrap_or_default()
+    }
+}
+
+#[test]
+fn clone() {
+    let db = Database::new();
+    db.set::<text>((), "hello".into());
+    let len: usize = length(&db);
+    assert_eq!(len, 5);
+}
+
+#[test]
+fn borrow() {
+    let mut db = Database::new();
+    assert_eq!(first_word(&db, "none"), "none");
+
+    db.set::<text>((), "hello world".into());
+    let before: &[String] = words(&db);
+    db.set::<text>((), "bye".into());
+    assert_eq!(before, ["hello", "world"]);
+    assert_eq!(words(&db), &["bye"]);
+    assert_eq!(first_word(&db, "none"), "bye");
+    let word: &str = Word(0).text(&db);
+    assert_eq!(word, "bye");
+
+    db.release_outputs();
+    assert_eq!(words(&db), &["bye"]);
+}
@@ -0,0 +1,99 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Instant;
+use yeter::Database;
+
+thread_local! {
+    static LINE_COUNT_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::query]
+fn buffer(db: &Database, name: String) -> Option<String>;
+
+#[yeter::query]
+fn line_count(db: &Database, name: String) -> usize {
+    LINE_COUNT_CALLS.with(|calls| calls.set(calls.get() + 1));
+    let text = buffer(db, name);
+    text.as_deref().map_or(0, |text| text.lines().count())
+}
+
+#[yeter::query(ttl = "1h")]
+fn fib(db: &Database, n: u64) -> u64 {
+    if n < 2 {
+        n
+    } else {
+        *fib(db, n - 1) + *fib(db, n - 2)
+    }
+}
+
+fn calls() -> usize {
+    LINE_COUNT_CALLS.with(Cell::get)
+}
+
+#[test]
+fn shadowed_inputs() {
+    let base = Rc::new(Database::new());
+    base.set::<buffer>(("a".into(),), Some("1\n2".into()));
+    base.set::<buffer>(("b".into(),), Some("1".into()));
+
+    let overlay = Database::overlay(&base);
+    overlay.set::<buffer>(("a".into(),), Some("1\n2\n3".into()));
+
+    assert_eq!(*line_count(&overlay, "a".into()), 3);
+    assert_eq!(*line_count(&overlay, "b".into()), 1);
+    assert_eq!(*line_count(&base, "a".into()), 2);
+    assert_eq!(*line_count(&base, "b".into()), 1);
+
+    base.set::<buffer>(("b".into(),), Some("1\n2\n3\n4".into()));
+    assert_eq!(*line_count(&overlay, "b".into()), 4);
+    assert_eq!(*line_count(&overlay, "a".into()), 3);
+}
+
+#[test]
+fn shared_results() {
+    let base = Rc::new(Database::new());
+    base.set::<buffer>(("a".into(),), Some("1\n2".into()));
+    base.set::<buffer>(("b".into(),), Some("1".into()));
+
+    let first = Database::overlay(&base);
+    let second = Database::overlay(&base);
+    first.set::<buffer>(("a".into(),), Some("".into()));
+
+    let before = calls();
+    assert_eq!(*line_count(&first, "a".into()), 0);
+    assert_eq!(*line_count(&first, "b".into()), 1);
+    assert_eq!(calls() - before, 2);
+
+    // The result for "b" doesn't depend on the shadowed input, so it was cached in the base
+    assert_eq!(*line_count(&second, "b".into()), 1);
+    assert_eq!(*line_count(&base, "b".into()), 1);
+    assert_eq!(calls() - before, 2);
+
+    assert_eq!(*line_count(&second, "a".into()), 2);
+    assert_eq!(calls() - before, 3);
+}
+
+#[test]
+fn base_items_verified_once() {
+    // Verifying an item with a time-to-live reads the clock, which counts the visited items
+    let base = Rc::new(Database::new());
+    let reads = Rc::new(Cell::new(0));
+    let now = Instant::now();
+    base.set_clock({
+        let reads = reads.clone();
+        move || {
+            reads.set(reads.get() + 1);
+            now
+        }
+    });
+    assert_eq!(*fib(&base, 24), 46368);
+
+    let overlay = Database::overlay(&base);
+    reads.set(0);
+    assert_eq!(*fib(&overlay, 24), 46368);
+    assert!(reads.get() < 100, "{} items visited", reads.get());
+
+    reads.set(0);
+    assert_eq!(*fib(&overlay, 24), 46368);
+    assert!(reads.get() < 5, "{} items visited", reads.get());
+}
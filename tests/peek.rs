@@ -0,0 +1,49 @@
+use std::cell::Cell;
+use yeter::Database;
+
+thread_local! {
+    static SUMS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::query]
+fn list(db: &Database) -> Option<Vec<usize>>;
+
+#[yeter::query]
+fn sum(db: &Database) -> usize {
+    SUMS.with(|sums| sums.set(sums.get() + 1));
+    let list = list(db);
+    list.as_ref().as_deref().unwrap_or_default().iter().sum()
+}
+
+#[yeter::query]
+fn logged_sum(db: &Database) -> Option<usize> {
+    db.peek::<sum>(()).map(|sum| *sum)
+}
+
+#[test]
+fn cached_only() {
+    let db = Database::new();
+    db.set::<list>((), Some(vec![1, 2]));
+    assert_eq!(db.peek::<sum>(()), None);
+    assert_eq!(SUMS.with(Cell::get), 0);
+
+    assert_eq!(*sum(&db), 3);
+    assert_eq!(db.peek::<sum>(()).as_deref(), Some(&3));
+
+    db.set::<list>((), Some(vec![1, 2, 3]));
+    assert_eq!(db.peek::<sum>(()), None);
+    assert_eq!(SUMS.with(Cell::get), 1);
+}
+
+#[test]
+fn no_dependency() {
+    let db = Database::new();
+    db.set::<list>((), Some(vec![1, 2]));
+    assert_eq!(*sum(&db), 3);
+    assert_eq!(*logged_sum(&db), Some(3));
+
+    // Even if sum changes, logged_sum didn't depend on it
+    db.set::<list>((), Some(vec![5]));
+    assert_eq!(*sum(&db), 5);
+    assert_eq!(*logged_sum(&db), Some(3));
+}
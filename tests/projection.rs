@@ -0,0 +1,51 @@
+use std::cell::Cell;
+use yeter::Database;
+
+thread_local! {
+    static EXPORT_COUNTS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Clone, PartialEq)]
+struct Analysis {
+    exports: Vec<String>,
+    line_count: usize,
+}
+
+#[yeter::input(default = String::new())]
+fn module_source(db: &Database, module: u32) -> String;
+
+#[yeter::query]
+fn analyze_module(db: &Database, module: u32) -> Analysis {
+    let source = module_source(db, module);
+    Analysis {
+        exports: source
+            .lines()
+            .filter_map(|line| line.strip_prefix("pub "))
+            .map(str::to_owned)
+            .collect(),
+        line_count: source.lines().count(),
+    }
+}
+
+#[yeter::query]
+fn export_count(db: &Database, module: u32) -> usize {
+    EXPORT_COUNTS.with(|calls| calls.set(calls.get() + 1));
+    db.select::<analyze_module, _, _>((module,), |analysis| &analysis.exports)
+        .len()
+}
+
+#[test]
+fn cutoff_on_selected_part() {
+    let db = Database::new();
+    db.set::<module_source>((0,), "pub a\nb".into());
+    assert_eq!(*export_count(&db, 0), 1);
+
+    db.set::<module_source>((0,), "pub a\nb\nc".into());
+    assert_eq!(analyze_module(&db, 0).line_count, 3);
+    assert_eq!(*export_count(&db, 0), 1);
+    assert_eq!(EXPORT_COUNTS.with(Cell::get), 1);
+
+    db.set::<module_source>((0,), "pub a\npub b".into());
+    assert_eq!(*export_count(&db, 0), 2);
+    assert_eq!(EXPORT_COUNTS.with(Cell::get), 2);
+}
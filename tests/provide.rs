@@ -0,0 +1,64 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use yeter::Database;
+
+thread_local! {
+    static LOADS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::query]
+fn library_source(db: &Database, name: String) -> Option<String>;
+
+#[yeter::query]
+fn import_len(db: &Database, name: String) -> usize {
+    library_source(db, name).as_deref().map_or(0, str::len)
+}
+
+fn load(name: String) -> Option<String> {
+    LOADS.with(|loads| loads.set(loads.get() + 1));
+    match name.as_str() {
+        "core" => Some("fn id(x) = x".into()),
+        _ => None,
+    }
+}
+
+#[test]
+fn lazy_loading() {
+    let db = Database::new();
+    db.provide::<library_source>(|(name,)| load(name));
+
+    assert_eq!(*import_len(&db, "core".into()), 12);
+    assert_eq!(*import_len(&db, "missing".into()), 0);
+    assert_eq!(*import_len(&db, "core".into()), 12);
+    assert_eq!(LOADS.with(Cell::get), 2);
+
+    db.set::<library_source>(("core".into(),), Some("".into()));
+    assert_eq!(*import_len(&db, "core".into()), 0);
+    assert_eq!(LOADS.with(Cell::get), 2);
+}
+
+#[yeter::query]
+fn borrowed_input<'a>(db: &Database, name: &'a str) -> Option<usize>;
+
+#[test]
+fn without_provider() {
+    let db = Database::new();
+    assert_eq!(*library_source(&db, "core".into()), None);
+    assert_eq!(*borrowed_input(&db, "core"), None);
+}
+
+#[test]
+fn overlay_provider() {
+    let base = Rc::new(Database::new());
+    base.provide::<library_source>(|(name,)| Some(format!("base:{}", name)));
+    let overlay = Database::overlay(&base);
+    overlay.provide::<library_source>(|(name,)| Some(format!("overlay:{}", name)));
+    let other = Database::overlay(&base);
+
+    assert_eq!(
+        *library_source(&overlay, "a".into()),
+        Some("overlay:a".into())
+    );
+    assert_eq!(*library_source(&base, "a".into()), Some("base:a".into()));
+    assert_eq!(*library_source(&other, "a".into()), Some("base:a".into()));
+}
@@ -0,0 +1,91 @@
+use std::cell::Cell;
+use yeter::{Database, Tracked};
+
+thread_local! {
+    static ANALYZE_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(PartialEq)]
+struct FunctionDef {
+    name: String,
+    body: String,
+}
+
+#[yeter::query]
+fn source(db: &Database) -> Option<String>;
+
+#[yeter::query]
+fn functions(db: &Database) -> Vec<Tracked<FunctionDef>> {
+    let source = source(db);
+    let source = Option::as_ref(&source).map_or("", String::as_str);
+    source
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, body)| {
+            let fields = FunctionDef {
+                name: name.to_owned(),
+                body: body.to_owned(),
+            };
+            db.new_tracked(name, fields)
+        })
+        .collect()
+}
+
+#[yeter::query]
+fn body_len(db: &Database, function: Tracked<FunctionDef>) -> usize {
+    ANALYZE_CALLS.with(|calls| calls.set(calls.get() + 1));
+    db.tracked(function).body.len()
+}
+
+#[test]
+fn stable_ids() {
+    let db = Database::new();
+    db.set::<source>((), Some("f:ab\ng:abc".into()));
+    let [f, g] = functions(&db).as_slice().try_into().unwrap();
+    assert_eq!(db.tracked(f).name, "f");
+    assert_eq!(*body_len(&db, f), 2);
+    assert_eq!(*body_len(&db, g), 3);
+
+    db.set::<source>((), Some("f:ab\ng:abcd\nh:".into()));
+    let fns = functions(&db);
+    assert_eq!(fns[..2], [f, g]);
+    assert_eq!(*body_len(&db, f), 2);
+    assert_eq!(*body_len(&db, g), 4);
+    assert_eq!(*body_len(&db, fns[2]), 0);
+    assert_eq!(ANALYZE_CALLS.with(Cell::get), 4);
+}
+
+#[test]
+#[should_panic(expected = "tracked entities can only be created by queries")]
+fn outside_query() {
+    let db = Database::new();
+    db.new_tracked("f", ());
+}
+
+#[test]
+#[should_panic(expected = "didn't create it again")]
+fn removed_entity() {
+    let db = Database::new();
+    db.set::<source>((), Some("f:ab\ng:abc".into()));
+    let [_, g] = functions(&db).as_slice().try_into().unwrap();
+
+    db.set::<source>((), Some("f:ab".into()));
+    assert_eq!(functions(&db).len(), 1);
+    db.tracked(g);
+}
+
+#[test]
+fn outdated_creator() {
+    let db = Database::new();
+    db.set::<source>((), Some("f:ab".into()));
+    let [f] = functions(&db).as_slice().try_into().unwrap();
+    assert_eq!(*body_len(&db, f), 2);
+
+    // The query that created `f` is re-executed without being called again
+    db.set::<source>((), Some("f:abc".into()));
+    assert_eq!(*body_len(&db, f), 3);
+
+    db.set::<source>((), Some("f:abc\ng:".into()));
+    assert_eq!(*body_len(&db, f), 3);
+    assert_eq!(ANALYZE_CALLS.with(Cell::get), 2);
+}
@@ -0,0 +1,36 @@
+use std::cell::Cell;
+use yeter::Database;
+
+thread_local! {
+    static TRIM_CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::input(default = String::new())]
+fn name(db: &Database) -> String;
+
+#[yeter::query(transparent)]
+fn trimmed_name(db: &Database) -> String {
+    TRIM_CALLS.with(|calls| calls.set(calls.get() + 1));
+    name(db).trim().to_owned()
+}
+
+#[yeter::query]
+fn greeting(db: &Database) -> String {
+    format!("Hello, {}!", trimmed_name(db))
+}
+
+#[test]
+fn dependencies_propagate() {
+    let db = Database::new();
+    db.set::<name>((), " world ".into());
+    assert_eq!(*greeting(&db), "Hello, world!");
+    assert_eq!(*greeting(&db), "Hello, world!");
+    assert_eq!(TRIM_CALLS.with(Cell::get), 1);
+    assert_eq!(db.peek::<trimmed_name>(()), None);
+
+    trimmed_name(&db);
+    assert_eq!(TRIM_CALLS.with(Cell::get), 2);
+
+    db.set::<name>((), "yeter".into());
+    assert_eq!(*greeting(&db), "Hello, yeter!");
+}
@@ -0,0 +1,114 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use yeter::Database;
+
+thread_local! {
+    static INSTALLED: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    static SCANS: Cell<usize> = const { Cell::new(0) };
+    static REPORTS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn install(name: &'static str) {
+    INSTALLED.with(|installed| installed.borrow_mut().push(name));
+}
+
+fn count(counter: &'static std::thread::LocalKey<Cell<usize>>) -> usize {
+    counter.with(Cell::get)
+}
+
+fn incr(counter: &'static std::thread::LocalKey<Cell<usize>>) {
+    counter.with(|c| c.set(c.get() + 1));
+}
+
+/// Makes the database use a clock that only moves when the returned function is called
+fn fake_clock(db: &Database) -> impl Fn(u64) {
+    let now = Rc::new(Cell::new(Instant::now()));
+    db.set_clock({
+        let now = now.clone();
+        move || now.get()
+    });
+    move |millis| now.set(now.get() + Duration::from_millis(millis))
+}
+
+#[yeter::query(ttl = "50ms")]
+fn toolchains(_db: &Database) -> Vec<&'static str> {
+    incr(&SCANS);
+    INSTALLED.with(|installed| installed.borrow().clone())
+}
+
+#[yeter::query]
+fn report(db: &Database) -> String {
+    incr(&REPORTS);
+    toolchains(db).join(", ")
+}
+
+#[test]
+fn expiry_and_early_cutoff() {
+    let db = Database::new();
+    let sleep = fake_clock(&db);
+    install("stable");
+    assert_eq!(*report(&db), "stable");
+    assert_eq!((count(&SCANS), count(&REPORTS)), (1, 1));
+
+    // Still fresh
+    sleep(40);
+    assert_eq!(*report(&db), "stable");
+    assert_eq!((count(&SCANS), count(&REPORTS)), (1, 1));
+
+    // Expired, but the result didn't change: the report is not recomputed
+    sleep(20);
+    assert_eq!(*report(&db), "stable");
+    assert_eq!((count(&SCANS), count(&REPORTS)), (2, 1));
+
+    install("nightly");
+    sleep(60);
+    assert_eq!(*report(&db), "stable, nightly");
+    assert_eq!((count(&SCANS), count(&REPORTS)), (3, 2));
+}
+
+thread_local! {
+    static DISK: Cell<u32> = const { Cell::new(0) };
+    static DISK_REPORTS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[yeter::query(ttl = "1h")]
+fn disk(_db: &Database) -> u32 {
+    DISK.with(Cell::get)
+}
+
+#[yeter::query]
+fn disk_report(db: &Database) -> String {
+    incr(&DISK_REPORTS);
+    format!("disk {}", disk(db))
+}
+
+#[yeter::query]
+fn free_space(_db: &Database) -> u32 {
+    DISK.with(Cell::get)
+}
+
+#[test]
+fn runtime_ttl() {
+    let db = Database::new();
+    let sleep = fake_clock(&db);
+    db.set_ttl::<disk>(Some(Duration::from_millis(20)));
+
+    assert_eq!(*disk_report(&db), "disk 0");
+    sleep(30);
+    assert_eq!(*disk_report(&db), "disk 0");
+    assert_eq!(count(&DISK_REPORTS), 1);
+
+    DISK.with(|disk| disk.set(1));
+    assert_eq!(*disk_report(&db), "disk 0");
+    sleep(30);
+    assert_eq!(*disk_report(&db), "disk 1");
+    assert_eq!(count(&DISK_REPORTS), 2);
+}
+
+#[test]
+#[should_panic(expected = "declared with one")]
+fn runtime_ttl_without_declared_ttl() {
+    let db = Database::new();
+    db.set_ttl::<free_space>(Some(Duration::from_millis(20)));
+}
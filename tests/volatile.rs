@@ -0,0 +1,57 @@
+use std::cell::Cell;
+use yeter::Database;
+
+thread_local! {
+    static CLOCK: Cell<u64> = const { Cell::new(0) };
+}
+
+fn tick() {
+    CLOCK.with(|clock| clock.set(clock.get() + 1));
+}
+
+#[yeter::query(volatile)]
+fn now(_db: &Database) -> u64 {
+    CLOCK.with(Cell::get)
+}
+
+#[yeter::query]
+fn uptime_message(db: &Database) -> String {
+    format!("up for {}s", now(db))
+}
+
+#[yeter::query]
+fn clock_untracked(db: &Database) -> u64 {
+    db.report_untracked_read();
+    CLOCK.with(Cell::get)
+}
+
+#[yeter::query]
+fn other_input(db: &Database) -> Option<()>;
+
+#[test]
+fn volatile() {
+    let db = Database::new();
+    let before = *now(&db);
+    assert_eq!(*uptime_message(&db), format!("up for {before}s"));
+
+    tick();
+    // Still the same revision
+    assert_eq!(*now(&db), before);
+    assert_eq!(*uptime_message(&db), format!("up for {before}s"));
+
+    db.new_revision();
+    assert_eq!(*now(&db), before + 1);
+    assert_eq!(*uptime_message(&db), format!("up for {}s", before + 1));
+}
+
+#[test]
+fn untracked_read() {
+    let db = Database::new();
+    let before = *clock_untracked(&db);
+
+    tick();
+    assert_eq!(*clock_untracked(&db), before);
+
+    db.set::<other_input>((), Some(()));
+    assert_eq!(*clock_untracked(&db), before + 1);
+}